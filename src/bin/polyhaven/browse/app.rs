@@ -0,0 +1,418 @@
+//! The state of the browser, and how it responds to input and messages.
+
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, time::{Duration, Instant}};
+
+use polyhaven::{
+    data::{asset::{AssetInfo, AssetType}, files::{Files, Resolution}},
+    request::{assets, categories},
+    Client
+};
+use ratatui::{
+    crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    widgets::{ListState, TableState}
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{file_list, total_size};
+
+use super::{queue::{Job, QueueEvent}, Message};
+
+/// How long an asset has to stay selected before its files are fetched, so
+/// scrolling through the list doesn't fetch every asset passed over.
+const SETTLE: Duration = Duration::from_millis(200);
+
+/// How far page up and page down move.
+const PAGE: isize = 10;
+
+pub enum Loadable<T> {
+    Loading,
+    Loaded(T),
+    Failed(String)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Categories,
+    Assets,
+    Files
+}
+
+/// The files of one resolution and format, such as every `jpg` map at `4k`.
+pub struct FileRow {
+    pub resolution: Resolution,
+    pub format: String,
+    /// The size of every file, including those packages include.
+    pub size: u64,
+    pub files: usize
+}
+
+pub struct Queued {
+    pub label: String,
+    pub state: JobState
+}
+
+pub enum JobState {
+    Waiting,
+    Running {
+        done: u64,
+        total: u64
+    },
+    Done(String),
+    Failed(String)
+}
+
+pub struct App {
+    client: Client,
+    messages: UnboundedSender<Message>,
+    jobs: UnboundedSender<Job>,
+    pub asset_type: AssetType,
+    /// Sorted by download count, most downloaded first.
+    pub assets: Loadable<Vec<AssetInfo>>,
+    /// Sorted by how many assets each has, so `all` comes first.
+    pub categories: Loadable<Vec<(String, u32)>>,
+    pub files: HashMap<String, Loadable<Files>>,
+    /// The resolutions and formats of each asset that are in the library.
+    pub installed: HashMap<String, BTreeSet<(Resolution, String)>>,
+    pub queue: Vec<Queued>,
+    pub focus: Pane,
+    pub category_state: ListState,
+    pub asset_state: TableState,
+    pub file_state: TableState,
+    pub search: String,
+    pub searching: bool,
+    /// A message for the footer, cleared by the next key press.
+    pub status: Option<String>,
+    selected_at: Instant,
+    quit: bool
+}
+
+impl App {
+    pub fn new(
+        client: Client,
+        asset_type: AssetType,
+        installed: HashMap<String, BTreeSet<(Resolution, String)>>,
+        messages: UnboundedSender<Message>,
+        jobs: UnboundedSender<Job>
+    ) -> Self {
+        Self {
+            client,
+            messages,
+            jobs,
+            asset_type,
+            assets: Loadable::Loading,
+            categories: Loadable::Loading,
+            files: HashMap::new(),
+            installed,
+            queue: Vec::new(),
+            focus: Pane::Assets,
+            category_state: ListState::default(),
+            asset_state: TableState::default(),
+            file_state: TableState::default(),
+            search: String::new(),
+            searching: false,
+            status: None,
+            selected_at: Instant::now(),
+            quit: false
+        }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Fetches the assets and categories of the current asset type.
+    pub fn load(&mut self) {
+        self.assets = Loadable::Loading;
+        self.categories = Loadable::Loading;
+        let asset_type = self.asset_type;
+
+        let (client, messages) = (self.client.clone(), self.messages.clone());
+        tokio::spawn(async move {
+            let params = assets::Params { asset_type: Some(asset_type), categories: vec![], author: None, search: vec![] };
+            let result = client.assets(&params).await.map_err(|err| err.to_string());
+            let _ = messages.send(Message::Assets(asset_type, result));
+        });
+        let (client, messages) = (self.client.clone(), self.messages.clone());
+        tokio::spawn(async move {
+            let params = categories::Params { asset_type, in_categories: vec![] };
+            let result = client.categories(&params).await.map_err(|err| err.to_string());
+            let _ = messages.send(Message::Categories(asset_type, result));
+        });
+    }
+
+    pub fn handle(&mut self, message: Message) {
+        match message {
+            Message::Input(Event::Key(key)) => self.key(key),
+            Message::Input(_) => {},
+            Message::Assets(asset_type, result) if asset_type == self.asset_type => {
+                self.assets = match result {
+                    Ok(assets) => {
+                        let mut assets = assets.into_values().collect::<Vec<_>>();
+                        assets.sort_by(|a, b| b.download_count.cmp(&a.download_count).then_with(|| a.id.cmp(&b.id)));
+                        Loadable::Loaded(assets)
+                    },
+                    Err(err) => Loadable::Failed(err)
+                };
+                self.select_asset(0);
+            },
+            Message::Categories(asset_type, result) if asset_type == self.asset_type => {
+                self.categories = match result {
+                    Ok(counts) => {
+                        let mut counts = counts.into_iter().collect::<Vec<_>>();
+                        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                        Loadable::Loaded(counts)
+                    },
+                    Err(err) => Loadable::Failed(err)
+                };
+                self.category_state.select(Some(0));
+                self.select_asset(0);
+            },
+            // Results for an asset type that's no longer shown.
+            Message::Assets(..) | Message::Categories(..) => {},
+            Message::Files(id, result) => {
+                let files = match result {
+                    Ok(files) => Loadable::Loaded(*files),
+                    Err(err) => Loadable::Failed(err)
+                };
+                self.files.insert(id, files);
+            },
+            Message::Queue(event) => self.queue_event(event)
+        }
+    }
+
+    /// Fetches the selected asset's files once it's been selected for a
+    /// moment.
+    pub fn tick(&mut self) {
+        if self.selected_at.elapsed() < SETTLE {
+            return;
+        }
+        let Some(info) = self.selected_asset() else {
+            return;
+        };
+        if self.files.contains_key(&info.id) {
+            return;
+        }
+        let info = info.clone();
+        self.files.insert(info.id.clone(), Loadable::Loading);
+        let (client, messages) = (self.client.clone(), self.messages.clone());
+        tokio::spawn(async move {
+            let result = client.files_for(&info).await.map(Box::new).map_err(|err| err.to_string());
+            let _ = messages.send(Message::Files(info.id, result));
+        });
+    }
+
+    /// The selected category, or `None` if every asset is shown.
+    pub fn selected_category(&self) -> Option<&str> {
+        let Loadable::Loaded(categories) = &self.categories else {
+            return None;
+        };
+        let (category, _) = categories.get(self.category_state.selected()?)?;
+        Some(category.as_str()).filter(|category| *category != "all")
+    }
+
+    /// The assets in the selected category that match the search.
+    pub fn visible_assets(&self) -> Vec<&AssetInfo> {
+        let Loadable::Loaded(assets) = &self.assets else {
+            return Vec::new();
+        };
+        let category = self.selected_category();
+        let search = self.search.to_lowercase();
+        assets.iter()
+            .filter(|asset| category.is_none_or(|category| asset.categories.iter().any(|has| has == category)))
+            .filter(|asset| {
+                search.is_empty()
+                    || asset.id.contains(&search)
+                    || asset.name.to_lowercase().contains(&search)
+                    || asset.tags.iter().any(|tag| tag.to_lowercase().contains(&search))
+            })
+            .collect()
+    }
+
+    pub fn selected_asset(&self) -> Option<&AssetInfo> {
+        self.visible_assets().get(self.asset_state.selected()?).copied()
+    }
+
+    /// The file rows of the selected asset, if its files have been fetched.
+    pub fn selected_files(&self) -> Option<Vec<FileRow>> {
+        match self.files.get(&self.selected_asset()?.id)? {
+            Loadable::Loaded(files) => Some(file_rows(files)),
+            _ => None
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        self.status = None;
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        if self.searching {
+            match key.code {
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Esc => {
+                    self.searching = false;
+                    self.search.clear();
+                },
+                KeyCode::Backspace => {
+                    self.search.pop();
+                },
+                KeyCode::Char(c) => self.search.push(c),
+                _ => return
+            }
+            self.select_asset(0);
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => self.cycle_focus(1),
+            KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => self.cycle_focus(2),
+            KeyCode::Enter if self.focus != Pane::Files => self.cycle_focus(1),
+            KeyCode::Enter | KeyCode::Char('d') => self.queue_selected(),
+            KeyCode::Char('/') => {
+                self.searching = true;
+                self.focus = Pane::Assets;
+            },
+            KeyCode::Char('t') => {
+                self.asset_type = match self.asset_type {
+                    AssetType::HDRI => AssetType::Texture,
+                    AssetType::Texture => AssetType::Model,
+                    AssetType::Model => AssetType::HDRI
+                };
+                self.category_state.select(Some(0));
+                self.load();
+            },
+            KeyCode::Char('r') => {
+                self.files.clear();
+                self.load();
+            },
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-PAGE),
+            KeyCode::PageDown => self.move_selection(PAGE),
+            KeyCode::Home | KeyCode::Char('g') => self.move_selection(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_selection(isize::MAX),
+            _ => {}
+        }
+    }
+
+    fn cycle_focus(&mut self, by: usize) {
+        let panes = [Pane::Categories, Pane::Assets, Pane::Files];
+        let index = panes.iter().position(|pane| *pane == self.focus).unwrap_or(0);
+        self.focus = panes[(index + by) % panes.len()];
+    }
+
+    fn move_selection(&mut self, by: isize) {
+        match self.focus {
+            Pane::Categories => {
+                let len = match &self.categories {
+                    Loadable::Loaded(categories) => categories.len(),
+                    _ => 0
+                };
+                self.category_state.select(step(self.category_state.selected(), len, by));
+                self.select_asset(0);
+            },
+            Pane::Assets => {
+                let len = self.visible_assets().len();
+                if let Some(index) = step(self.asset_state.selected(), len, by) {
+                    self.select_asset(index);
+                }
+            },
+            Pane::Files => {
+                let len = self.selected_files().map_or(0, |rows| rows.len());
+                self.file_state.select(step(self.file_state.selected(), len, by));
+            }
+        }
+    }
+
+    fn select_asset(&mut self, index: usize) {
+        let len = self.visible_assets().len();
+        self.asset_state.select(Some(index).filter(|index| *index < len));
+        self.file_state.select(Some(0));
+        self.selected_at = Instant::now();
+    }
+
+    /// Queues the selected resolution and format of the selected asset.
+    fn queue_selected(&mut self) {
+        let Some(info) = self.selected_asset() else {
+            return;
+        };
+        let files = match self.files.get(&info.id) {
+            Some(Loadable::Loaded(files)) => files,
+            _ => {
+                self.status = Some(format!("The files of {} haven't been fetched yet", info.id));
+                return;
+            }
+        };
+        let rows = file_rows(files);
+        let Some(row) = self.file_state.selected().and_then(|index| rows.get(index)) else {
+            return;
+        };
+        let job = Job { info: info.clone(), files: files.clone(), resolution: row.resolution, format: row.format.clone() };
+        let label = format!("{} {} {}", info.id, row.resolution, row.format);
+        self.status = Some(format!("Queued {}", label));
+        self.queue.push(Queued { label, state: JobState::Waiting });
+        let _ = self.jobs.send(job);
+    }
+
+    fn queue_event(&mut self, event: QueueEvent) {
+        let running = self.queue.iter_mut().find(|queued| matches!(queued.state, JobState::Running { .. }));
+        match event {
+            QueueEvent::Started => {
+                if let Some(waiting) = self.queue.iter_mut().find(|queued| matches!(queued.state, JobState::Waiting)) {
+                    waiting.state = JobState::Running { done: 0, total: 0 };
+                }
+            },
+            QueueEvent::Progress { done, total } => {
+                if let Some(running) = running {
+                    running.state = JobState::Running { done, total };
+                }
+            },
+            QueueEvent::Finished { id, result, installed } => {
+                if let Some(running) = running {
+                    running.state = match result {
+                        Ok(summary) => JobState::Done(summary),
+                        Err(err) => JobState::Failed(err)
+                    };
+                }
+                match installed.is_empty() {
+                    true => self.installed.remove(&id),
+                    false => self.installed.insert(id, installed)
+                };
+            }
+        }
+    }
+}
+
+/// Moves `selected` by `by` within a list of `len` items, stopping at either
+/// end.
+fn step(selected: Option<usize>, len: usize, by: isize) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    let index = selected.unwrap_or(0).saturating_add_signed(by).min(len - 1);
+    Some(index)
+}
+
+/// The files of an asset grouped by resolution and format, which is how
+/// they're installed. Files without a resolution, like HDRI backplates,
+/// aren't included.
+pub fn file_rows(files: &Files) -> Vec<FileRow> {
+    let mut rows = BTreeMap::<(Resolution, String), FileRow>::new();
+    for (_, resolution, format, file) in file_list(files) {
+        let Some(resolution) = resolution else {
+            continue;
+        };
+        let row = rows.entry((resolution, format.clone()))
+            .or_insert(FileRow { resolution, format, size: 0, files: 0 });
+        row.size += total_size(file);
+        row.files += 1;
+    }
+    rows.into_values().collect()
+}
\ No newline at end of file
@@ -0,0 +1,81 @@
+//! The `browse` command: an interactive terminal UI for the catalog, built
+//! with the `tui` feature.
+//!
+//! Everything is drawn with text, and only the keyboard is used, so it works
+//! the same over SSH as it does locally.
+
+use std::{collections::HashMap, path::PathBuf, thread, time::Duration};
+
+use polyhaven::{data::{asset::{AssetInfo, AssetType}, files::Files}, library::Library, Client};
+use ratatui::crossterm::event::{self, Event};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::CliResult;
+
+use self::{app::App, queue::QueueEvent};
+
+mod app;
+mod queue;
+mod ui;
+
+/// How often the UI checks whether the selected asset has settled, so its
+/// files can be fetched.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Everything the UI reacts to. Requests are made in tasks, which send their
+/// results back as messages so the UI never waits on the network.
+pub enum Message {
+    Input(Event),
+    Assets(AssetType, Result<HashMap<String, AssetInfo>, String>),
+    Categories(AssetType, Result<HashMap<String, u32>, String>),
+    Files(String, Result<Box<Files>, String>),
+    Queue(QueueEvent)
+}
+
+pub async fn run(client: Client, asset_type: AssetType, library: PathBuf, concurrency: usize) -> CliResult {
+    let library = Library::open(library).await?;
+    let (messages, mut receiver) = mpsc::unbounded_channel();
+    let installed = library.manifest().assets.keys()
+        .map(|id| (id.clone(), queue::installed(&library, id)))
+        .collect();
+    let jobs = queue::spawn(client.clone(), library, concurrency, messages.clone());
+    let mut app = App::new(client, asset_type, installed, messages.clone(), jobs);
+    app.load();
+
+    let mut terminal = ratatui::try_init()?;
+    read_input(messages);
+    let mut tick = tokio::time::interval(TICK);
+    let result = async {
+        while !app.should_quit() {
+            terminal.draw(|frame| ui::draw(frame, &mut app))?;
+            tokio::select! {
+                message = receiver.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    app.handle(message);
+                    // Progress arrives in bursts, so handle everything that's
+                    // waiting before drawing again.
+                    while let Ok(message) = receiver.try_recv() {
+                        app.handle(message);
+                    }
+                },
+                _ = tick.tick() => app.tick()
+            }
+        }
+        CliResult::Ok(())
+    }.await;
+    ratatui::restore();
+    result
+}
+
+/// Reads terminal input on its own thread, since reading blocks.
+fn read_input(messages: UnboundedSender<Message>) {
+    thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if messages.send(Message::Input(event)).is_err() {
+                break;
+            }
+        }
+    });
+}
\ No newline at end of file
@@ -0,0 +1,91 @@
+//! Installs queued downloads into the library, one asset at a time.
+
+use std::collections::BTreeSet;
+
+use polyhaven::{
+    data::{asset::AssetInfo, files::{Files, Resolution}},
+    download::{DownloadEvent, Progress},
+    library::{InstallReport, Library, Selection},
+    Client
+};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use super::Message;
+
+/// One resolution and format of an asset to install.
+pub struct Job {
+    pub info: AssetInfo,
+    pub files: Files,
+    pub resolution: Resolution,
+    pub format: String
+}
+
+pub enum QueueEvent {
+    Started,
+    Progress {
+        done: u64,
+        total: u64
+    },
+    /// A job finished, with a summary of what happened and the resolutions
+    /// and formats of the asset that are now in the library.
+    Finished {
+        id: String,
+        result: Result<String, String>,
+        installed: BTreeSet<(Resolution, String)>
+    }
+}
+
+/// Starts installing jobs sent to the returned sender, in order. Events are
+/// sent to `messages` as `Message::Queue`.
+pub fn spawn(client: Client, mut library: Library, concurrency: usize, messages: UnboundedSender<Message>) -> UnboundedSender<Job> {
+    let (jobs, mut receiver) = mpsc::unbounded_channel::<Job>();
+    tokio::spawn(async move {
+        while let Some(job) = receiver.recv().await {
+            let _ = messages.send(Message::Queue(QueueEvent::Started));
+            let selection = Selection { resolutions: vec![job.resolution], formats: vec![job.format], maps: vec![] };
+            let progress = messages.clone();
+            let result = library.install(&client, &job.info, &job.files, &selection)
+                .concurrency(concurrency)
+                .progress(move |_: &DownloadEvent, overall: &Progress| {
+                    let _ = progress.send(Message::Queue(QueueEvent::Progress { done: overall.bytes_done, total: overall.bytes_total }));
+                })
+                .run()
+                .await;
+            let event = QueueEvent::Finished {
+                result: result.map(|report| summary(&report)).map_err(|err| err.to_string()),
+                installed: installed(&library, &job.info.id),
+                id: job.info.id
+            };
+            if messages.send(Message::Queue(event)).is_err() {
+                break;
+            }
+        }
+    });
+    jobs
+}
+
+/// The resolutions and formats of an asset that are in `library`.
+pub fn installed(library: &Library, id: &str) -> BTreeSet<(Resolution, String)> {
+    library.resolutions(id)
+        .into_iter()
+        .flat_map(|resolution| {
+            library.formats(id, resolution)
+                .into_iter()
+                .map(move |format| (resolution, format.to_string()))
+        })
+        .collect()
+}
+
+fn summary(report: &InstallReport) -> String {
+    let mut parts = vec![format!("{} downloaded", report.downloaded.len())];
+    if !report.unchanged.is_empty() {
+        parts.push(format!("{} already installed", report.unchanged.len()));
+    }
+    if !report.conflicts.is_empty() {
+        parts.push(format!("{} left alone as they were changed locally", report.conflicts.len()));
+    }
+    if let Some((path, err)) = report.failed.first() {
+        parts.push(format!("{} failed, like {}: {}", report.failed.len(), path, err));
+    }
+    parts.join(", ")
+}
\ No newline at end of file
@@ -0,0 +1,189 @@
+//! Drawing the browser.
+
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, Paragraph, Row, Table, Wrap},
+    Frame
+};
+
+use crate::{info_fields, table::format_size};
+
+use super::app::{App, JobState, Loadable, Pane};
+
+/// How many queued downloads are shown at once.
+const QUEUE_LINES: usize = 5;
+
+const KEYS: &str = "tab: pane  j/k: move  /: search  t: type  d: install  r: reload  q: quit";
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let queue_height = match app.queue.len() {
+        0 => 0,
+        len => len.min(QUEUE_LINES) as u16 + 2
+    };
+    let [main, queue, footer] = Layout::vertical([Constraint::Fill(1), Constraint::Length(queue_height), Constraint::Length(1)])
+        .areas(frame.area());
+    let [categories, assets, details] = Layout::horizontal([Constraint::Length(28), Constraint::Fill(1), Constraint::Fill(1)])
+        .areas(main);
+    draw_categories(frame, app, categories);
+    draw_assets(frame, app, assets);
+    draw_details(frame, app, details);
+    draw_queue(frame, app, queue);
+    draw_footer(frame, app, footer);
+}
+
+fn draw_categories(frame: &mut Frame, app: &mut App, area: Rect) {
+    let block = pane(" Categories ", app.focus == Pane::Categories);
+    let categories = match &app.categories {
+        Loadable::Loaded(categories) => categories,
+        loadable => return draw_loadable(frame, block, loadable, "categories", area)
+    };
+    let width = area.width.saturating_sub(4) as usize;
+    let items = categories.iter()
+        .map(|(category, count)| {
+            let count = count.to_string();
+            let name_width = width.saturating_sub(count.len() + 1);
+            ListItem::new(format!("{:name_width$.name_width$} {}", category, count, name_width = name_width))
+        })
+        .collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(highlight(app.focus == Pane::Categories));
+    frame.render_stateful_widget(list, area, &mut app.category_state);
+}
+
+fn draw_assets(frame: &mut Frame, app: &mut App, area: Rect) {
+    let total = match &app.assets {
+        Loadable::Loaded(assets) => assets.len(),
+        loadable => {
+            let block = pane(format!(" {} ", app.asset_type), app.focus == Pane::Assets);
+            return draw_loadable(frame, block, loadable, "assets", area);
+        }
+    };
+    let visible = app.visible_assets();
+    let mut title = format!(" {} ({} of {})", app.asset_type, visible.len(), total);
+    if !app.search.is_empty() {
+        title.push_str(&format!(" matching \"{}\"", app.search));
+    }
+    title.push(' ');
+    let rows = visible.iter()
+        .map(|asset| {
+            let installed = match app.installed.contains_key(&asset.id) {
+                true => "*",
+                false => " "
+            };
+            Row::new([installed.to_string(), asset.name.clone(), asset.download_count.to_string()])
+        })
+        .collect::<Vec<_>>();
+    let table = Table::new(rows, [Constraint::Length(1), Constraint::Fill(1), Constraint::Length(9)])
+        .header(Row::new(["", "NAME", "DOWNLOADS"]).bold())
+        .block(pane(title, app.focus == Pane::Assets))
+        .row_highlight_style(highlight(app.focus == Pane::Assets));
+    frame.render_stateful_widget(table, area, &mut app.asset_state);
+}
+
+fn draw_details(frame: &mut Frame, app: &mut App, area: Rect) {
+    let Some(info) = app.selected_asset() else {
+        frame.render_widget(pane(" Details ", false), area);
+        return;
+    };
+    let fields = info_fields(app.client(), info);
+    let label_width = fields.iter().map(|(label, _)| label.len()).max().unwrap_or(0) + 2;
+    let lines = fields.iter()
+        .map(|(label, value)| Line::from(vec![
+            Span::styled(format!("{:label_width$}", label, label_width = label_width), Style::new().fg(Color::DarkGray)),
+            Span::raw(value.clone())
+        ]))
+        .collect::<Vec<_>>();
+    let [info_area, files_area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 4), Constraint::Fill(1)]).areas(area);
+    let paragraph = Paragraph::new(lines)
+        .block(pane(format!(" {} ", info.name), false))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, info_area);
+
+    let installed = app.installed.get(&info.id).cloned().unwrap_or_default();
+    let block = pane(" Files ", app.focus == Pane::Files);
+    let rows = match app.files.get(&info.id) {
+        Some(Loadable::Loaded(_)) => app.selected_files().unwrap_or_default(),
+        Some(loadable) => return draw_loadable(frame, block, loadable, "files", files_area),
+        None => return draw_loadable(frame, block, &Loadable::<()>::Loading, "files", files_area)
+    };
+    let rows = rows.into_iter()
+        .map(|row| {
+            let status = match installed.contains(&(row.resolution, row.format.clone())) {
+                true => "installed",
+                false => ""
+            };
+            Row::new([row.resolution.to_string(), row.format, row.files.to_string(), format_size(row.size), status.to_string()])
+        })
+        .collect::<Vec<_>>();
+    let widths = [Constraint::Length(5), Constraint::Length(6), Constraint::Length(5), Constraint::Length(10), Constraint::Fill(1)];
+    let table = Table::new(rows, widths)
+        .header(Row::new(["RES", "FORMAT", "FILES", "SIZE", ""]).bold())
+        .block(block)
+        .row_highlight_style(highlight(app.focus == Pane::Files));
+    frame.render_stateful_widget(table, files_area, &mut app.file_state);
+}
+
+fn draw_queue(frame: &mut Frame, app: &App, area: Rect) {
+    if area.height == 0 {
+        return;
+    }
+    // Show the most recent jobs, which include the one running.
+    let start = app.queue.len().saturating_sub(QUEUE_LINES);
+    let lines = app.queue[start..].iter()
+        .map(|queued| {
+            let state = match &queued.state {
+                JobState::Waiting => Span::styled("waiting", Style::new().fg(Color::DarkGray)),
+                JobState::Running { done, total } => {
+                    let percent = match *total {
+                        0 => 0,
+                        total => done * 100 / total
+                    };
+                    Span::styled(format!("{}% of {}", percent, format_size(*total)), Style::new().fg(Color::Yellow))
+                },
+                JobState::Done(summary) => Span::styled(summary.clone(), Style::new().fg(Color::Green)),
+                JobState::Failed(err) => Span::styled(err.clone(), Style::new().fg(Color::Red))
+            };
+            Line::from(vec![Span::raw(format!("{}  ", queued.label)), state])
+        })
+        .collect::<Vec<_>>();
+    frame.render_widget(Paragraph::new(lines).block(pane(" Downloads ", false)), area);
+}
+
+fn draw_footer(frame: &mut Frame, app: &App, area: Rect) {
+    let line = match (&app.status, app.searching) {
+        (_, true) => Line::from(format!("/{}", app.search)),
+        (Some(status), false) => Line::from(status.as_str()),
+        (None, false) => Line::from(KEYS).fg(Color::DarkGray)
+    };
+    frame.render_widget(Paragraph::new(line), area);
+    if app.searching {
+        frame.set_cursor_position((area.x + 1 + app.search.chars().count() as u16, area.y));
+    }
+}
+
+/// Draws a pane whose contents are loading or failed to load.
+fn draw_loadable<T>(frame: &mut Frame, block: Block<'_>, loadable: &Loadable<T>, what: &str, area: Rect) {
+    let paragraph = match loadable {
+        Loadable::Failed(err) => Paragraph::new(format!("Couldn't fetch {}: {}", what, err)).red(),
+        _ => Paragraph::new(format!("Fetching {}...", what)).fg(Color::DarkGray)
+    };
+    frame.render_widget(paragraph.block(block).wrap(Wrap { trim: false }), area);
+}
+
+fn pane<'a>(title: impl Into<Line<'a>>, focused: bool) -> Block<'a> {
+    let block = Block::bordered().title(title);
+    match focused {
+        true => block.border_style(Style::new().fg(Color::Cyan)),
+        false => block
+    }
+}
+
+fn highlight(focused: bool) -> Style {
+    match focused {
+        true => Style::new().add_modifier(Modifier::REVERSED),
+        false => Style::new().add_modifier(Modifier::BOLD)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,440 @@
+//! The `polyhaven` command line tool, built with the `cli` feature.
+
+use std::{collections::{BTreeMap, HashMap}, error::Error, path::{Path, PathBuf}, process::ExitCode};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use polyhaven::{
+    data::{asset::{Asset, AssetInfo, AssetType}, files::{FileData, Files, HDRIFormat, Resolution, TextureFormat, TextureMap, TextureMaps}, select::{MapSet, ResolutionPolicy}},
+    download::{DownloadEvent, Progress},
+    request::{assets, categories},
+    Client
+};
+
+#[cfg(feature = "tui")]
+mod browse;
+mod table;
+
+use self::table::{format_size, print_fields, print_table};
+
+type CliResult<T = ()> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(name = "polyhaven", version, about = "Search, inspect and download PolyHaven assets")]
+struct Cli {
+    /// Print JSON instead of tables.
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// The API to use, if not PolyHaven's own, such as a mirror.
+    #[arg(long, global = true, value_name = "URL")]
+    api_url: Option<String>,
+
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists the assets matching every filter.
+    Search {
+        /// Words that must each appear in an asset's name, tags or categories.
+        terms: Vec<String>,
+        /// hdris, textures or models.
+        #[arg(long = "type", short = 't')]
+        asset_type: Option<AssetType>,
+        /// Only assets in this category. May be repeated or comma-separated.
+        #[arg(long = "category", short = 'c', value_delimiter = ',')]
+        categories: Vec<String>,
+        /// Only assets by this author id.
+        #[arg(long, short)]
+        author: Option<String>,
+        /// Only assets with this exact tag. May be repeated or comma-separated.
+        #[arg(long = "tag", value_delimiter = ',')]
+        tags: Vec<String>
+    },
+    /// Shows an asset's details.
+    Info {
+        id: String
+    },
+    /// Lists an asset's files.
+    Files {
+        id: String
+    },
+    /// Lists the categories of an asset type, with how many assets are in each.
+    Categories {
+        /// hdris, textures or models.
+        asset_type: AssetType,
+        /// Only count assets that are also in these categories.
+        #[arg(long = "in", value_delimiter = ',')]
+        in_categories: Vec<String>
+    },
+    /// Shows an author's details.
+    Author {
+        id: String
+    },
+    /// Downloads an asset's files, printing the path of each one.
+    Download {
+        id: String,
+        /// The resolution to download, like 4k.
+        #[arg(long = "res", default_value = "4k")]
+        resolution: Resolution,
+        /// The file format, like exr or jpg, or blend, gltf or fbx for a
+        /// package with its textures. Defaults to hdr for HDRIs, jpg for
+        /// textures and gltf for models.
+        #[arg(long, short)]
+        format: Option<String>,
+        /// The texture maps to download, comma-separated. Defaults to all.
+        #[arg(long, value_delimiter = ',')]
+        maps: Vec<TextureMap>,
+        /// What to do if the resolution isn't available.
+        #[arg(long, value_enum, default_value_t = Policy::Nearest)]
+        policy: Policy,
+        /// The directory to download into.
+        #[arg(long, short, default_value = ".")]
+        out: PathBuf,
+        /// How many files to download at once.
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize
+    },
+    /// Browses the catalog interactively, installing assets into a library.
+    #[cfg(feature = "tui")]
+    Browse {
+        /// The asset type to start with.
+        #[arg(long = "type", short = 't', default_value = "hdris")]
+        asset_type: AssetType,
+        /// The library to install into, created if it doesn't exist.
+        #[arg(long, short, default_value = ".")]
+        library: PathBuf,
+        /// How many files to download at once.
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Policy {
+    Exact,
+    Nearest,
+    AtMost,
+    AtLeast
+}
+
+impl From<Policy> for ResolutionPolicy {
+    fn from(policy: Policy) -> Self {
+        match policy {
+            Policy::Exact => Self::Exact,
+            Policy::Nearest => Self::Nearest,
+            Policy::AtMost => Self::AtMost,
+            Policy::AtLeast => Self::AtLeast
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut builder = Client::builder();
+    if let Some(api_url) = cli.api_url {
+        builder = builder.api_url(api_url);
+    }
+    let result = match builder.build() {
+        Ok(client) => run(&client, cli.command, cli.json).await,
+        Err(err) => Err(err.into())
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(client: &Client, command: Command, json: bool) -> CliResult {
+    match command {
+        Command::Search { terms, asset_type, categories, author, tags } => search(client, terms, asset_type, categories, author, tags, json).await,
+        Command::Info { id } => info(client, &id, json).await,
+        Command::Files { id } => files(client, &id, json).await,
+        Command::Categories { asset_type, in_categories } => categories(client, asset_type, in_categories, json).await,
+        Command::Author { id } => author(client, &id, json).await,
+        Command::Download { id, resolution, format, maps, policy, out, concurrency } => {
+            download(client, &id, resolution, format, &maps, policy.into(), &out, concurrency, json).await
+        },
+        #[cfg(feature = "tui")]
+        Command::Browse { asset_type, library, concurrency } => browse::run(client.clone(), asset_type, library, concurrency).await
+    }
+}
+
+async fn search(
+    client: &Client,
+    terms: Vec<String>,
+    asset_type: Option<AssetType>,
+    categories: Vec<String>,
+    author: Option<String>,
+    tags: Vec<String>,
+    json: bool
+) -> CliResult {
+    // The API has no tag filter, but searches tags, so tags narrow the search
+    // and are then matched exactly here.
+    let search = terms.into_iter().chain(tags.iter().cloned()).collect();
+    let params = assets::Params { asset_type, categories, author, search };
+    let assets = client.assets(&params).await?
+        .into_iter()
+        .filter(|(_, asset)| tags.iter().all(|tag| asset.tags.iter().any(|has| has.eq_ignore_ascii_case(tag))))
+        .collect::<BTreeMap<_, _>>();
+    if json {
+        return print_json(&assets);
+    }
+
+    let mut assets = assets.into_values().collect::<Vec<_>>();
+    assets.sort_by(|a, b| b.download_count.cmp(&a.download_count).then_with(|| a.id.cmp(&b.id)));
+    let rows = assets.iter()
+        .map(|asset| vec![
+            asset.id.clone(),
+            asset.name.clone(),
+            type_name(asset),
+            asset.download_count.to_string(),
+            asset.categories.join(", ")
+        ])
+        .collect::<Vec<_>>();
+    print_table(&["id", "name", "type", "downloads", "categories"], &rows);
+    Ok(())
+}
+
+async fn info(client: &Client, id: &str, json: bool) -> CliResult {
+    let info = client.info(id).await?;
+    if json {
+        return print_json(&info);
+    }
+
+    print_fields(&info_fields(client, &info));
+    Ok(())
+}
+
+/// An asset's details as labelled values, including those specific to its
+/// type.
+fn info_fields(client: &Client, info: &AssetInfo) -> Vec<(&'static str, String)> {
+    let mut authors = info.authors.iter().map(|(name, role)| format!("{} ({})", name, role)).collect::<Vec<_>>();
+    authors.sort();
+    let mut fields = vec![
+        ("id", info.id.clone()),
+        ("name", info.name.clone()),
+        ("type", type_name(info)),
+        ("published", info.date_published.format("%Y-%m-%d").to_string()),
+        ("downloads", info.download_count.to_string()),
+        ("authors", authors.join(", ")),
+        ("categories", info.categories.join(", ")),
+        ("tags", info.tags.join(", "))
+    ];
+    match &info.asset {
+        Asset::HDRI(hdri) => {
+            fields.push(("evs", hdri.evs_cap.to_string()));
+            fields.push(("whitebalance", hdri.whitebalance.map_or_else(|| "-".to_string(), |kelvin| format!("{}K", kelvin))));
+            fields.push(("backplates", if hdri.backplates { "yes" } else { "no" }.to_string()));
+            if let Some((lat, lon)) = hdri.coords {
+                fields.push(("coords", format!("{}, {}", lat, lon)));
+            }
+        },
+        Asset::Texture(texture) => fields.push(("dimensions", format!("{} x {}", texture.dimensions.0, texture.dimensions.1))),
+        Asset::Model(_) | Asset::Unparsed => {}
+    }
+    fields.push(("thumbnail", client.thumbnail(info, 256)));
+    fields
+}
+
+async fn files(client: &Client, id: &str, json: bool) -> CliResult {
+    let info = client.info(id).await?;
+    let files = client.files_for(&info).await?;
+    if json {
+        return print_json(&files);
+    }
+
+    let rows = file_list(&files)
+        .into_iter()
+        .map(|(kind, resolution, format, file)| vec![
+            kind,
+            resolution.map(|resolution| resolution.to_string()).unwrap_or_default(),
+            format,
+            format_size(total_size(file)),
+            file.url.clone()
+        ])
+        .collect::<Vec<_>>();
+    print_table(&["kind", "res", "format", "size", "url"], &rows);
+    Ok(())
+}
+
+async fn categories(client: &Client, asset_type: AssetType, in_categories: Vec<String>, json: bool) -> CliResult {
+    let counts = client.categories(&categories::Params { asset_type, in_categories }).await?;
+    if json {
+        return print_json(&counts.into_iter().collect::<BTreeMap<_, _>>());
+    }
+
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let rows = counts.into_iter()
+        .map(|(category, count)| vec![category, count.to_string()])
+        .collect::<Vec<_>>();
+    print_table(&["category", "assets"], &rows);
+    Ok(())
+}
+
+async fn author(client: &Client, id: &str, json: bool) -> CliResult {
+    let author = client.author(id).await?;
+    if json {
+        return print_json(&author);
+    }
+
+    let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+    print_fields(&[
+        ("name", author.name.clone()),
+        ("link", optional(&author.link)),
+        ("email", optional(&author.email)),
+        ("donate", optional(&author.donate))
+    ]);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn download(
+    client: &Client,
+    id: &str,
+    resolution: Resolution,
+    format: Option<String>,
+    maps: &[TextureMap],
+    policy: ResolutionPolicy,
+    out: &Path,
+    concurrency: usize,
+    json: bool
+) -> CliResult {
+    let info = client.info(id).await?;
+    let files = client.files_for(&info).await?;
+    let not_found = |format: &str| format!("{} has no {} file near {}", id, format, resolution);
+
+    let downloads = match &files {
+        Files::HDRI(hdri) => {
+            let format = format.unwrap_or_else(|| "hdr".to_string());
+            let selected = hdri.select(resolution, policy, &[format.parse::<HDRIFormat>()?]).ok_or_else(|| not_found(&format))?;
+            vec![selected.file]
+        },
+        Files::Texture(texture) => {
+            let format = format.unwrap_or_else(|| "jpg".to_string());
+            let packages = [("blend", &texture.blend), ("gltf", &texture.gltf)];
+            select_textures(&packages, &format, resolution, policy, |formats| texture.map_set(maps, resolution, policy, formats))
+                .ok_or_else(|| not_found(&format))?
+        },
+        Files::Model(model) => {
+            let format = format.unwrap_or_else(|| "gltf".to_string());
+            let packages = [("blend", &model.blend), ("gltf", &model.gltf), ("fbx", &model.fbx)];
+            select_textures(&packages, &format, resolution, policy, |formats| model.map_set(maps, resolution, policy, formats))
+                .ok_or_else(|| not_found(&format))?
+        }
+    };
+
+    let paths = stream::iter(downloads)
+        .map(|file| client.download(file).progress(report_progress).to_dir(out))
+        .buffer_unordered(concurrency.max(1))
+        .try_collect::<Vec<_>>()
+        .await?;
+    if json {
+        return print_json(&paths);
+    }
+    for path in paths {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// The package in `format` if it's a package format, otherwise the texture
+/// maps in `format`, chosen by `map_set`. Returns `None` if there's nothing to
+/// download.
+fn select_textures<'a>(
+    packages: &[(&str, &'a HashMap<Resolution, FileData>)],
+    format: &str,
+    resolution: Resolution,
+    policy: ResolutionPolicy,
+    map_set: impl FnOnce(&[TextureFormat]) -> MapSet<'a>
+) -> Option<Vec<&'a FileData>> {
+    if let Some((_, files)) = packages.iter().find(|(name, _)| name.eq_ignore_ascii_case(format)) {
+        let (_, file) = policy.select(resolution, files)?;
+        return Some(vec![file]);
+    }
+    let format = format.parse::<TextureFormat>().unwrap_or_else(|never| match never {});
+    let set = map_set(&[format]);
+    for map in &set.missing {
+        eprintln!("warning: no {} file for {}", set.resolution, map);
+    }
+    for fallback in &set.fallbacks {
+        eprintln!("note: using {} {} for {}", fallback.resolution, fallback.format, fallback.map);
+    }
+    let files = set.maps.values().map(|selected| selected.file).collect::<Vec<_>>();
+    Some(files).filter(|files| !files.is_empty())
+}
+
+fn report_progress(event: &DownloadEvent, _overall: &Progress) {
+    match event {
+        DownloadEvent::Finished { url } => eprintln!("downloaded {}", url),
+        DownloadEvent::Failed { url, error } => eprintln!("failed {}: {}", url, error),
+        _ => {}
+    }
+}
+
+/// Every file in a listing as `(kind, resolution, format, file)`, sorted.
+/// The kind is the texture map or package name for textures and models.
+fn file_list(files: &Files) -> FileList<'_> {
+    let mut list = Vec::new();
+    match files {
+        Files::HDRI(hdri) => {
+            for (resolution, formats) in &hdri.hdri {
+                for (format, file) in formats {
+                    list.push(("hdri".to_string(), Some(*resolution), format.to_string(), file));
+                }
+            }
+            for (name, formats) in &hdri.backplates {
+                for (format, file) in formats {
+                    list.push((format!("backplate {}", name), None, format.to_string(), file));
+                }
+            }
+            for (kind, file) in [("colorchart", &hdri.colorchart), ("tonemapped", &hdri.tonemapped)] {
+                if let Some(file) = file {
+                    list.push((kind.to_string(), None, String::new(), file));
+                }
+            }
+        },
+        Files::Texture(texture) => add_maps(&mut list, &[("blend", &texture.blend), ("gltf", &texture.gltf)], &texture.maps),
+        Files::Model(model) => add_maps(&mut list, &[("blend", &model.blend), ("gltf", &model.gltf), ("fbx", &model.fbx)], &model.maps)
+    }
+    list.sort_by(|a, b| (&a.0, a.1, &a.2).cmp(&(&b.0, b.1, &b.2)));
+    list
+}
+
+type FileList<'a> = Vec<(String, Option<Resolution>, String, &'a FileData)>;
+
+fn add_maps<'a>(list: &mut FileList<'a>, packages: &[(&str, &'a HashMap<Resolution, FileData>)], maps: &'a TextureMaps) {
+    for (name, files) in packages {
+        for (resolution, file) in files.iter() {
+            list.push((name.to_string(), Some(*resolution), name.to_string(), file));
+        }
+    }
+    for (map, resolutions) in maps {
+        for (resolution, formats) in resolutions {
+            for (format, file) in formats {
+                list.push((map.to_string(), Some(*resolution), format.to_string(), file));
+            }
+        }
+    }
+}
+
+/// The size of a file and everything it includes.
+fn total_size(file: &FileData) -> u64 {
+    file.size + file.include.values().map(total_size).sum::<u64>()
+}
+
+fn type_name(asset: &AssetInfo) -> String {
+    asset.asset.asset_type().map_or_else(|| "unknown".to_string(), |asset_type| asset_type.to_string())
+}
+
+fn print_json(value: &impl serde::Serialize) -> CliResult {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
\ No newline at end of file
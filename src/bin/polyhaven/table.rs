@@ -0,0 +1,53 @@
+//! Plain text output, for when `--json` isn't given.
+
+/// Prints rows in aligned columns under a header. The last column isn't
+/// padded, so long values like URLs don't push out trailing whitespace.
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths = headers.iter().map(|header| header.chars().count()).collect::<Vec<_>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    print_row(&widths, headers.iter().map(|header| header.to_uppercase()));
+    for row in rows {
+        print_row(&widths, row.iter().cloned());
+    }
+}
+
+fn print_row(widths: &[usize], cells: impl Iterator<Item = String>) {
+    let cells = cells.collect::<Vec<_>>();
+    let last = cells.len().saturating_sub(1);
+    let line = cells.iter()
+        .enumerate()
+        .map(|(index, cell)| match index == last {
+            true => cell.clone(),
+            false => format!("{:width$}", cell, width = widths[index])
+        })
+        .collect::<Vec<_>>()
+        .join("  ");
+    println!("{}", line);
+}
+
+/// Prints labelled values, one per line, with the values lined up.
+pub fn print_fields(fields: &[(&str, String)]) {
+    let width = fields.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    for (label, value) in fields {
+        println!("{:width$}  {}", format!("{}:", label), value, width = width + 1);
+    }
+}
+
+/// A size in bytes, like `12.3 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, UNITS[unit])
+    }
+}
\ No newline at end of file
@@ -0,0 +1,182 @@
+//! A synchronous client for use without an async runtime, enabled by the
+//! `blocking` feature.
+//!
+//! `BlockingClient` has the same endpoints as `Client` and returns the same
+//! `data` types. It is built on `reqwest::blocking`, so it must not be used
+//! from inside an async runtime.
+
+use std::{collections::HashMap, sync::Arc};
+
+use bytes::Bytes;
+use reqwest::StatusCode;
+use url::Url;
+
+use crate::{data, endpoints::{log_warnings, Endpoints}, request::{assets, categories}, retry::{self, RetryPolicy}, throttle::Throttle, ClientBuilder, Error, ParseMode, Parsed, Result};
+
+/// A blocking handle to the PolyHaven API. See `Client` for details of each
+/// endpoint.
+///
+/// Built with `ClientBuilder::build_blocking`, which accepts the same
+/// configuration as `Client`. Rate limits are applied, but `max_in_flight` is
+/// not, since each request blocks its calling thread anyway.
+#[derive(Debug, Clone)]
+pub struct BlockingClient {
+    pub(crate) http: reqwest::blocking::Client,
+    pub(crate) endpoints: Endpoints,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) throttle: Arc<Throttle>
+}
+
+impl BlockingClient {
+    /// Creates a client with the default configuration.
+    ///
+    /// # Panics
+    /// Panics if the underlying HTTP client can't be initialised. Use
+    /// `BlockingClient::builder` to handle this.
+    pub fn new() -> Self {
+        Self::builder().build_blocking().expect("Couldn't build PolyHaven client")
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    pub fn api_url(&self) -> &Url {
+        &self.endpoints.api_url
+    }
+
+    pub fn cdn_url(&self) -> &Url {
+        &self.endpoints.cdn_url
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    pub fn parse_mode(&self) -> ParseMode {
+        self.endpoints.parse_mode
+    }
+
+    pub fn assets_url(&self, params: &assets::Params) -> Url {
+        self.endpoints.assets_url(params)
+    }
+
+    pub fn info_url(&self, id: &str) -> Url {
+        self.endpoints.info_url(id)
+    }
+
+    pub fn files_url(&self, id: &str) -> Url {
+        self.endpoints.files_url(id)
+    }
+
+    pub fn author_url(&self, id: &str) -> Url {
+        self.endpoints.author_url(id)
+    }
+
+    pub fn categories_url(&self, params: &categories::Params) -> Url {
+        self.endpoints.categories_url(params)
+    }
+
+    pub fn assets(&self, params: &assets::Params) -> Result<HashMap<String, data::asset::AssetInfo>> {
+        self.assets_with_warnings(params).map(log_warnings)
+    }
+
+    pub fn assets_with_warnings(&self, params: &assets::Params) -> Result<Parsed<HashMap<String, data::asset::AssetInfo>>> {
+        let url = self.assets_url(params);
+        let body = self.get_body(&url)?;
+        self.endpoints.parse_assets(&url, &body)
+    }
+
+    pub fn info(&self, id: &str) -> Result<data::asset::AssetInfo> {
+        self.info_with_warnings(id).map(log_warnings)
+    }
+
+    pub fn info_with_warnings(&self, id: &str) -> Result<Parsed<data::asset::AssetInfo>> {
+        let url = self.info_url(id);
+        let body = self.get_body(&url)?;
+        self.endpoints.parse_info(&url, &body, id)
+    }
+
+    pub fn files(&self, id: &str) -> Result<data::files::Files> {
+        self.files_with_warnings(id).map(log_warnings)
+    }
+
+    pub fn files_with_warnings(&self, id: &str) -> Result<Parsed<data::files::Files>> {
+        let info_url = self.info_url(id);
+        let info_body = self.get_body(&info_url)?;
+        let asset_type = self.endpoints.parse_info_asset_type(&info_url, &info_body)?;
+        self.files_of_type_with_warnings(id, &asset_type)
+    }
+
+    pub fn files_of_type(&self, id: &str, asset_type: &data::asset::AssetType) -> Result<data::files::Files> {
+        self.files_of_type_with_warnings(id, asset_type).map(log_warnings)
+    }
+
+    pub fn files_of_type_with_warnings(&self, id: &str, asset_type: &data::asset::AssetType) -> Result<Parsed<data::files::Files>> {
+        let url = self.files_url(id);
+        let body = self.get_body(&url)?;
+        self.endpoints.parse_files(&url, &body, id, asset_type)
+    }
+
+    pub fn files_for(&self, info: &data::asset::AssetInfo) -> Result<data::files::Files> {
+        self.files_for_with_warnings(info).map(log_warnings)
+    }
+
+    pub fn files_for_with_warnings(&self, info: &data::asset::AssetInfo) -> Result<Parsed<data::files::Files>> {
+        match info.asset.asset_type() {
+            Some(asset_type) => self.files_of_type_with_warnings(&info.id, &asset_type),
+            None => self.files_by_shape_with_warnings(&info.id)
+        }
+    }
+
+    pub fn files_by_shape(&self, id: &str) -> Result<data::files::Files> {
+        self.files_by_shape_with_warnings(id).map(log_warnings)
+    }
+
+    pub fn files_by_shape_with_warnings(&self, id: &str) -> Result<Parsed<data::files::Files>> {
+        let url = self.files_url(id);
+        let body = self.get_body(&url)?;
+        self.endpoints.parse_files_by_shape(&url, &body, id)
+    }
+
+    pub fn author(&self, id: &str) -> Result<data::author::Author> {
+        let url = self.author_url(id);
+        let body = self.get_body(&url)?;
+        self.endpoints.parse_author(&url, &body)
+    }
+
+    pub fn categories(&self, params: &categories::Params) -> Result<HashMap<String, u32>> {
+        let url = self.categories_url(params);
+        let body = self.get_body(&url)?;
+        self.endpoints.parse_categories(&url, &body)
+    }
+
+    pub fn thumbnail(&self, asset: &data::asset::AssetInfo, resolution: u32) -> String {
+        self.endpoints.thumbnail(asset, resolution)
+    }
+
+    fn get_body(&self, url: &Url) -> Result<Bytes> {
+        self.retry.run_blocking(url.as_str(), || self.get_bytes(url))
+    }
+
+    fn get_bytes(&self, url: &Url) -> Result<Bytes> {
+        self.throttle.limiter(url).acquire_blocking();
+        let resp = self.http.get(url.clone()).send()?;
+        let status = resp.status();
+        if status == StatusCode::NOT_FOUND {
+            return Err(Error::NotFound { url: url.to_string() });
+        }
+        if !status.is_success() {
+            let retry_after = retry::parse_retry_after(resp.headers());
+            let body = resp.text().unwrap_or_default();
+            return Err(Error::status(url.to_string(), status, retry_after, &body));
+        }
+        Ok(resp.bytes()?)
+    }
+}
+
+impl Default for BlockingClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
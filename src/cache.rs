@@ -0,0 +1,358 @@
+//! An optional on-disk cache of API responses.
+//!
+//! Responses are keyed by their normalized URL and kept fresh according to
+//! their `Cache-Control` header, or the cache's TTL if they don't have one.
+//! Stale responses are revalidated with `If-None-Match`/`If-Modified-Since`,
+//! so unchanged data isn't downloaded again. When the cache grows beyond its
+//! maximum size, the least recently used responses are evicted.
+
+use std::{collections::HashMap, io, path::{Path, PathBuf}, sync::atomic::{AtomicU64, Ordering}, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use bytes::Bytes;
+use md5::{Digest, Md5};
+use reqwest::header::{HeaderMap, HeaderValue, CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::download;
+
+/// How many times the cache has been used, and how.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Responses served from the cache without contacting the server.
+    pub hits: u64,
+    /// Responses that had to be downloaded, either because they weren't
+    /// cached or because the server had a newer version.
+    pub misses: u64,
+    /// Stale responses that the server confirmed were still current.
+    pub revalidations: u64,
+    /// Responses evicted to keep the cache under its maximum size.
+    pub evictions: u64
+}
+
+/// A cache of API responses in a directory on disk. Give one to
+/// `ClientBuilder::cache` to use it.
+///
+/// Failing to read or write the cache never fails a request; the problem is
+/// logged and the request goes to the server as if there were no cache.
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_size: u64,
+    index: Mutex<Option<Index>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    revalidations: AtomicU64,
+    evictions: AtomicU64
+}
+
+impl DiskCache {
+    /// A cache in `dir`, which is created when first needed. Responses
+    /// without a `Cache-Control` lifetime stay fresh for an hour, and the
+    /// cache is limited to 256 MiB.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: Duration::from_secs(60 * 60),
+            max_size: 256 * 1024 * 1024,
+            index: Mutex::new(None),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            revalidations: AtomicU64::new(0),
+            evictions: AtomicU64::new(0)
+        }
+    }
+
+    /// Sets how long responses stay fresh if the server doesn't say.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the most bytes of response bodies the cache may hold.
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            revalidations: self.revalidations.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Removes every cached response.
+    pub async fn clear(&self) -> io::Result<()> {
+        let mut index = self.index.lock().await;
+        *index = None;
+        match tokio::fs::remove_dir_all(&self.dir).await {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(())
+        }
+    }
+
+    /// Looks up the cached response for `url`, fresh or not.
+    pub(crate) async fn get(&self, url: &Url) -> Option<CachedResponse> {
+        let key = cache_key(url);
+        let meta = tokio::fs::read(self.meta_path(&key)).await.ok()?;
+        let meta = serde_json::from_slice::<EntryMeta>(&meta).ok()?;
+        let body = tokio::fs::read(self.body_path(&key)).await.ok()?;
+        Some(CachedResponse { key, meta, body: body.into() })
+    }
+
+    /// Serves a fresh cached response.
+    pub(crate) async fn hit(&self, cached: CachedResponse) -> Bytes {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        log::debug!("Cache hit for {}", cached.meta.url);
+        self.touch(cached.key, cached.meta).await;
+        cached.body
+    }
+
+    /// Serves a stale cached response after the server confirmed it's still
+    /// current, updating its freshness from the server's new headers.
+    pub(crate) async fn revalidated(&self, cached: CachedResponse, headers: &HeaderMap) -> Bytes {
+        self.revalidations.fetch_add(1, Ordering::Relaxed);
+        log::debug!("Cache revalidated {}", cached.meta.url);
+        let mut meta = cached.meta;
+        meta.stored_at = now();
+        meta.max_age = self.max_age(headers).unwrap_or(0);
+        if let Some(etag) = header_string(headers, ETAG) {
+            meta.etag = Some(etag);
+        }
+        if let Some(last_modified) = header_string(headers, LAST_MODIFIED) {
+            meta.last_modified = Some(last_modified);
+        }
+        self.touch(cached.key, meta).await;
+        cached.body
+    }
+
+    /// Records a response downloaded from the server, storing it if its
+    /// `Cache-Control` allows.
+    pub(crate) async fn miss(&self, url: &Url, headers: &HeaderMap, body: &Bytes) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        log::debug!("Cache miss for {}", url);
+        let key = cache_key(url);
+        let Some(max_age) = self.max_age(headers) else {
+            self.remove(&key).await;
+            return;
+        };
+        let meta = EntryMeta {
+            url: url.to_string(),
+            etag: header_string(headers, ETAG),
+            last_modified: header_string(headers, LAST_MODIFIED),
+            stored_at: now(),
+            max_age,
+            last_access: now(),
+            size: body.len() as u64
+        };
+        if let Err(err) = self.write(&key, &meta, Some(body)).await {
+            log::warn!("Couldn't write {} to the cache: {}", url, err);
+            return;
+        }
+        self.insert_and_evict(key, meta.size, meta.last_access).await;
+    }
+
+    /// How long a response with these headers stays fresh, or `None` if it
+    /// mustn't be stored at all.
+    fn max_age(&self, headers: &HeaderMap) -> Option<u64> {
+        let mut max_age = self.ttl.as_secs();
+        for value in headers.get_all(CACHE_CONTROL) {
+            let Ok(value) = value.to_str() else { continue };
+            for directive in value.split(',').map(|directive| directive.trim().to_ascii_lowercase()) {
+                if directive == "no-store" {
+                    return None;
+                } else if directive == "no-cache" {
+                    max_age = 0;
+                } else if let Some(seconds) = directive.strip_prefix("max-age=") {
+                    if let Ok(seconds) = seconds.trim_matches('"').parse() {
+                        max_age = seconds;
+                    }
+                }
+            }
+        }
+        Some(max_age)
+    }
+
+    async fn touch(&self, key: String, mut meta: EntryMeta) {
+        meta.last_access = now();
+        if let Err(err) = self.write(&key, &meta, None).await {
+            log::warn!("Couldn't update {} in the cache: {}", meta.url, err);
+        }
+        let mut index = self.index.lock().await;
+        if let Some(entry) = index.as_mut().and_then(|index| index.entries.get_mut(&key)) {
+            entry.1 = meta.last_access;
+        }
+    }
+
+    /// Writes an entry's body, if given, and then its meta file. Both are
+    /// replaced whole, and the meta file last, so an interrupted write never
+    /// leaves a meta file describing a body that isn't there.
+    async fn write(&self, key: &str, meta: &EntryMeta, body: Option<&Bytes>) -> io::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        if let Some(body) = body {
+            replace(&self.body_path(key), body).await?;
+        }
+        replace(&self.meta_path(key), &serde_json::to_vec(meta)?).await
+    }
+
+    async fn remove(&self, key: &str) {
+        let _ = tokio::fs::remove_file(self.meta_path(key)).await;
+        let _ = tokio::fs::remove_file(self.body_path(key)).await;
+        let mut index = self.index.lock().await;
+        if let Some(index) = index.as_mut() {
+            index.remove(key);
+        }
+    }
+
+    /// Adds an entry to the index, then evicts the least recently used
+    /// entries until the cache fits within its maximum size.
+    async fn insert_and_evict(&self, key: String, size: u64, last_access: u64) {
+        let mut index = self.index.lock().await;
+        if index.is_none() {
+            *index = Some(Index::load(&self.dir).await);
+        }
+        let index = index.as_mut().unwrap();
+        index.remove(&key);
+        index.total += size;
+        index.entries.insert(key, (size, last_access));
+
+        while index.total > self.max_size {
+            let Some(oldest) = index.entries.iter()
+                .min_by_key(|(_, (_, last_access))| *last_access)
+                .map(|(key, _)| key.clone())
+            else { break };
+            index.remove(&oldest);
+            let _ = tokio::fs::remove_file(self.meta_path(&oldest)).await;
+            let _ = tokio::fs::remove_file(self.body_path(&oldest)).await;
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+            log::debug!("Evicted {} from the cache", oldest);
+        }
+    }
+
+    fn meta_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn body_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.body", key))
+    }
+}
+
+/// A response found in the cache.
+#[derive(Debug)]
+pub(crate) struct CachedResponse {
+    key: String,
+    meta: EntryMeta,
+    body: Bytes
+}
+
+impl CachedResponse {
+    pub fn is_fresh(&self) -> bool {
+        now() < self.meta.stored_at.saturating_add(self.meta.max_age)
+    }
+
+    /// Headers asking the server to only send the response if it changed.
+    pub fn conditional_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = self.meta.etag.as_deref().and_then(|etag| HeaderValue::from_str(etag).ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = self.meta.last_modified.as_deref().and_then(|date| HeaderValue::from_str(date).ok()) {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+        headers
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EntryMeta {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// When the response was stored or last revalidated, in Unix seconds.
+    stored_at: u64,
+    /// How many seconds after `stored_at` the response stays fresh.
+    max_age: u64,
+    /// When the response was last used, in Unix seconds.
+    last_access: u64,
+    size: u64
+}
+
+/// The size and last access time of every cached response, so eviction
+/// doesn't have to scan the cache directory.
+#[derive(Debug, Default)]
+struct Index {
+    entries: HashMap<String, (u64, u64)>,
+    total: u64
+}
+
+impl Index {
+    async fn load(dir: &Path) -> Self {
+        let mut index = Self::default();
+        let Ok(mut read_dir) = tokio::fs::read_dir(dir).await else { return index };
+        while let Ok(Some(dir_entry)) = read_dir.next_entry().await {
+            let path = dir_entry.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let Some(key) = path.file_stem().and_then(|stem| stem.to_str()) else { continue };
+            let Ok(meta) = tokio::fs::read(&path).await else { continue };
+            let Ok(meta) = serde_json::from_slice::<EntryMeta>(&meta) else { continue };
+            index.total += meta.size;
+            index.entries.insert(key.to_string(), (meta.size, meta.last_access));
+        }
+        index
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((size, _)) = self.entries.remove(key) {
+            self.total -= size;
+        }
+    }
+}
+
+/// Names a cache entry after its URL, with the query sorted and the fragment
+/// removed so that equivalent URLs share an entry.
+fn cache_key(url: &Url) -> String {
+    let mut normalized = url.clone();
+    normalized.set_fragment(None);
+    let mut pairs = url.query_pairs().into_owned().collect::<Vec<_>>();
+    if pairs.is_empty() {
+        normalized.set_query(None);
+    } else {
+        pairs.sort();
+        normalized.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    format!("{:x}", Md5::digest(normalized.as_str().as_bytes()))
+}
+
+/// Writes `bytes` to a `.part` file next to `path`, then renames it over
+/// `path`.
+async fn replace(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let part = download::part_path(path);
+    let result = match tokio::fs::write(&part, bytes).await {
+        Ok(()) => tokio::fs::rename(&part, path).await,
+        Err(err) => Err(err)
+    };
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&part).await;
+    }
+    result
+}
+
+fn header_string(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers.get(name)?.to_str().ok().map(str::to_string)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
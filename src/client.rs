@@ -0,0 +1,510 @@
+use std::{collections::HashMap, sync::{Arc, OnceLock}, time::Duration};
+
+use bytes::Bytes;
+use reqwest::{header::{HeaderMap, HeaderValue, USER_AGENT}, StatusCode};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::{cache::DiskCache, data, endpoints::{self, log_warnings, Endpoints}, request::{assets, categories}, retry::{self, RetryPolicy}, snapshot::{Snapshot, SnapshotTransport}, throttle::{Permit, RateLimit, Throttle}, transport::{HttpRequest, HttpResponse, ReqwestTransport, Transport}, Error, ParseMode, Parsed, Result};
+
+/// The PolyHaven API that clients talk to unless configured otherwise.
+pub const DEFAULT_API_URL: &str = "https://api.polyhaven.com";
+/// The PolyHaven CDN that thumbnails are served from unless configured
+/// otherwise.
+pub const DEFAULT_CDN_URL: &str = "https://cdn.polyhaven.com";
+/// The User-Agent sent with every request unless configured otherwise.
+pub const DEFAULT_USER_AGENT: &str = concat!("polyhaven-rs/", env!("CARGO_PKG_VERSION"));
+
+/// A reusable handle to the PolyHaven API.
+///
+/// A client owns a single connection pool, so it should be created once and
+/// shared (it is cheap to clone) rather than created per request.
+///
+/// Requests are sent through a `Transport`, which is `reqwest` unless another
+/// one is given to `ClientBuilder::build_with_transport`.
+#[derive(Debug, Clone)]
+pub struct Client<T = ReqwestTransport> {
+    transport: T,
+    headers: HeaderMap,
+    endpoints: Endpoints,
+    retry: RetryPolicy,
+    throttle: Arc<Throttle>,
+    cache: Option<Arc<DiskCache>>
+}
+
+impl Client {
+    /// Creates a client with the default configuration.
+    ///
+    /// # Panics
+    /// Panics if the underlying HTTP client can't be initialised, in the same
+    /// way as `reqwest::Client::new`. Use `Client::builder` to handle this.
+    pub fn new() -> Self {
+        Self::builder().build().expect("Couldn't build PolyHaven client")
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Creates a client that answers every query from `snapshot`, without a
+    /// network. See `ClientBuilder::build_offline`.
+    pub fn offline(snapshot: Snapshot) -> Client<SnapshotTransport> {
+        Self::builder().build_offline(snapshot).expect("Couldn't build offline PolyHaven client")
+    }
+
+    /// The client used by the free functions in `request::*`.
+    pub(crate) fn shared() -> &'static Client<Arc<dyn Transport>> {
+        SHARED.get_or_init(|| Client::new().into_dyn())
+    }
+
+    /// Replaces the default shared client, unless it has already been used.
+    pub(crate) fn set_shared(client: Client<Arc<dyn Transport>>) -> bool {
+        SHARED.set(client).is_ok()
+    }
+}
+
+static SHARED: OnceLock<Client<Arc<dyn Transport>>> = OnceLock::new();
+
+impl<T: Transport> Client<T> {
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Boxes up this client's transport, so that clients with different
+    /// transports can be used interchangeably.
+    pub fn into_dyn(self) -> Client<Arc<dyn Transport>> {
+        Client {
+            transport: Arc::new(self.transport),
+            headers: self.headers,
+            endpoints: self.endpoints,
+            retry: self.retry,
+            throttle: self.throttle,
+            cache: self.cache
+        }
+    }
+
+    pub fn api_url(&self) -> &Url {
+        &self.endpoints.api_url
+    }
+
+    pub fn cdn_url(&self) -> &Url {
+        &self.endpoints.cdn_url
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    pub fn parse_mode(&self) -> ParseMode {
+        self.endpoints.parse_mode
+    }
+
+    /// The response cache, if one was configured. Its `stats` show whether
+    /// it's being used.
+    pub fn cache(&self) -> Option<&DiskCache> {
+        self.cache.as_deref()
+    }
+
+    /// The URL that `assets` requests for the given parameters.
+    pub fn assets_url(&self, params: &assets::Params) -> Url {
+        self.endpoints.assets_url(params)
+    }
+
+    pub fn info_url(&self, id: &str) -> Url {
+        self.endpoints.info_url(id)
+    }
+
+    pub fn files_url(&self, id: &str) -> Url {
+        self.endpoints.files_url(id)
+    }
+
+    pub fn author_url(&self, id: &str) -> Url {
+        self.endpoints.author_url(id)
+    }
+
+    /// The URL that `categories` requests for the given parameters.
+    pub fn categories_url(&self, params: &categories::Params) -> Url {
+        self.endpoints.categories_url(params)
+    }
+
+    pub async fn assets(&self, params: &assets::Params) -> Result<HashMap<String, data::asset::AssetInfo>> {
+        self.assets_with_warnings(params).await.map(log_warnings)
+    }
+
+    /// Like `assets`, but also returns any data that had to be skipped in
+    /// lenient mode.
+    pub async fn assets_with_warnings(&self, params: &assets::Params) -> Result<Parsed<HashMap<String, data::asset::AssetInfo>>> {
+        let url = self.assets_url(params);
+        let body = self.get_body(&url).await?;
+        self.endpoints.parse_assets(&url, &body)
+    }
+
+    pub async fn info(&self, id: &str) -> Result<data::asset::AssetInfo> {
+        self.info_with_warnings(id).await.map(log_warnings)
+    }
+
+    /// Like `info`, but also returns any data that had to be skipped in
+    /// lenient mode.
+    pub async fn info_with_warnings(&self, id: &str) -> Result<Parsed<data::asset::AssetInfo>> {
+        let url = self.info_url(id);
+        let body = self.get_body(&url).await?;
+        self.endpoints.parse_info(&url, &body, id)
+    }
+
+    /// Fetches an asset's files, looking up its type with an extra `/info`
+    /// request first. Prefer `files_of_type` or `files_for` if the type is
+    /// already known, or `files_by_shape` otherwise, which need only one
+    /// request.
+    pub async fn files(&self, id: &str) -> Result<data::files::Files> {
+        self.files_with_warnings(id).await.map(log_warnings)
+    }
+
+    /// Like `files`, but also returns any data that had to be skipped in
+    /// lenient mode.
+    pub async fn files_with_warnings(&self, id: &str) -> Result<Parsed<data::files::Files>> {
+        let info_url = self.info_url(id);
+        let info_body = self.get_body(&info_url).await?;
+        let asset_type = self.endpoints.parse_info_asset_type(&info_url, &info_body)?;
+        self.files_of_type_with_warnings(id, &asset_type).await
+    }
+
+    /// Fetches the files of an asset whose type is already known.
+    pub async fn files_of_type(&self, id: &str, asset_type: &data::asset::AssetType) -> Result<data::files::Files> {
+        self.files_of_type_with_warnings(id, asset_type).await.map(log_warnings)
+    }
+
+    /// Like `files_of_type`, but also returns any data that had to be skipped
+    /// in lenient mode.
+    pub async fn files_of_type_with_warnings(&self, id: &str, asset_type: &data::asset::AssetType) -> Result<Parsed<data::files::Files>> {
+        let url = self.files_url(id);
+        let body = self.get_body(&url).await?;
+        self.endpoints.parse_files(&url, &body, id, asset_type)
+    }
+
+    /// Fetches the files of an asset that has already been looked up. Falls
+    /// back to `files_by_shape` if the asset's type couldn't be parsed.
+    pub async fn files_for(&self, info: &data::asset::AssetInfo) -> Result<data::files::Files> {
+        self.files_for_with_warnings(info).await.map(log_warnings)
+    }
+
+    /// Like `files_for`, but also returns any data that had to be skipped in
+    /// lenient mode.
+    pub async fn files_for_with_warnings(&self, info: &data::asset::AssetInfo) -> Result<Parsed<data::files::Files>> {
+        match info.asset.asset_type() {
+            Some(asset_type) => self.files_of_type_with_warnings(&info.id, &asset_type).await,
+            None => self.files_by_shape_with_warnings(&info.id).await
+        }
+    }
+
+    /// Fetches an asset's files, working out its type from the keys of the
+    /// `/files` response itself.
+    pub async fn files_by_shape(&self, id: &str) -> Result<data::files::Files> {
+        self.files_by_shape_with_warnings(id).await.map(log_warnings)
+    }
+
+    /// Like `files_by_shape`, but also returns any data that had to be skipped
+    /// in lenient mode.
+    pub async fn files_by_shape_with_warnings(&self, id: &str) -> Result<Parsed<data::files::Files>> {
+        let url = self.files_url(id);
+        let body = self.get_body(&url).await?;
+        self.endpoints.parse_files_by_shape(&url, &body, id)
+    }
+
+    pub async fn author(&self, id: &str) -> Result<data::author::Author> {
+        let url = self.author_url(id);
+        let body = self.get_body(&url).await?;
+        self.endpoints.parse_author(&url, &body)
+    }
+
+    pub async fn categories(&self, params: &categories::Params) -> Result<HashMap<String, u32>> {
+        let url = self.categories_url(params);
+        let body = self.get_body(&url).await?;
+        self.endpoints.parse_categories(&url, &body)
+    }
+
+    /// The URL of an asset's thumbnail on this client's CDN.
+    pub fn thumbnail(&self, asset: &data::asset::AssetInfo, resolution: u32) -> String {
+        self.endpoints.thumbnail(asset, resolution)
+    }
+
+    /// Fetches a response as JSON, without converting it to `data` types.
+    pub(crate) async fn get_json<D: DeserializeOwned>(&self, url: &Url) -> Result<D> {
+        let body = self.get_body(url).await?;
+        endpoints::deserialize(url, &body)
+    }
+
+    /// Fetches the body of a GET request, retrying according to the policy
+    /// and going through the cache if there is one.
+    async fn get_body(&self, url: &Url) -> Result<Bytes> {
+        let Some(cache) = &self.cache else {
+            let no_headers = HeaderMap::new();
+            let resp = self.retry.run(url.as_str(), || self.get_bytes(url, &no_headers)).await?;
+            return Ok(resp.body);
+        };
+
+        let (cached, conditional) = match cache.get(url).await {
+            Some(cached) if cached.is_fresh() => return Ok(cache.hit(cached).await),
+            Some(cached) => {
+                let conditional = cached.conditional_headers();
+                (Some(cached), conditional)
+            },
+            None => (None, HeaderMap::new())
+        };
+        let resp = self.retry.run(url.as_str(), || self.get_bytes(url, &conditional)).await?;
+        match cached {
+            Some(cached) if resp.status == StatusCode::NOT_MODIFIED => Ok(cache.revalidated(cached, &resp.headers).await),
+            _ => {
+                cache.miss(url, &resp.headers, &resp.body).await;
+                Ok(resp.body)
+            }
+        }
+    }
+
+    /// Makes a single attempt at a GET request, failing on non-2xx statuses.
+    /// A 304 is only accepted if `extra_headers` made the request conditional.
+    async fn get_bytes(&self, url: &Url, extra_headers: &HeaderMap) -> Result<Fetched> {
+        let (_permit, resp) = self.send_get(url, extra_headers).await?;
+        Ok(Fetched {
+            status: resp.status,
+            headers: resp.headers,
+            body: resp.body.bytes().await?
+        })
+    }
+
+    /// Sends a single GET request without reading the body, failing on
+    /// non-2xx statuses as `get_bytes` does. The permit should be held until
+    /// the body has been read, so that it counts towards `max_in_flight`.
+    pub(crate) async fn send_get(&self, url: &Url, extra_headers: &HeaderMap) -> Result<(Permit, HttpResponse)> {
+        let permit = self.throttle.limiter(url).acquire().await;
+        let mut request = HttpRequest::get(url.clone());
+        request.headers = self.headers.clone();
+        request.headers.extend(extra_headers.clone());
+        let resp = self.transport.send(request).await?;
+        if resp.status == StatusCode::NOT_FOUND {
+            return Err(Error::NotFound { url: url.to_string() });
+        }
+        let not_modified = resp.status == StatusCode::NOT_MODIFIED && !extra_headers.is_empty();
+        if !resp.status.is_success() && !not_modified {
+            let retry_after = retry::parse_retry_after(&resp.headers);
+            let body = resp.body.bytes().await.unwrap_or_default();
+            return Err(Error::status(url.to_string(), resp.status, retry_after, &String::from_utf8_lossy(&body)));
+        }
+        Ok((permit, resp))
+    }
+}
+
+/// A successful response, read into memory.
+struct Fetched {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Configures and builds a `Client`.
+#[derive(Debug)]
+pub struct ClientBuilder {
+    api_url: String,
+    cdn_url: String,
+    user_agent: String,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    default_headers: HeaderMap,
+    retry: RetryPolicy,
+    api_rate_limit: RateLimit,
+    cdn_rate_limit: RateLimit,
+    host_rate_limits: HashMap<String, RateLimit>,
+    parse_mode: ParseMode,
+    cache: Option<DiskCache>
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self {
+            api_url: DEFAULT_API_URL.to_string(),
+            cdn_url: DEFAULT_CDN_URL.to_string(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            timeout: None,
+            connect_timeout: None,
+            default_headers: HeaderMap::new(),
+            retry: RetryPolicy::default(),
+            api_rate_limit: RateLimit::per_second(10.0).max_in_flight(8),
+            cdn_rate_limit: RateLimit::unlimited(),
+            host_rate_limits: HashMap::new(),
+            parse_mode: ParseMode::default(),
+            cache: None
+        }
+    }
+
+    /// Sets the base URL that API endpoints are resolved against, for example
+    /// to point at a local mock server.
+    pub fn api_url(mut self, url: impl Into<String>) -> Self {
+        self.api_url = url.into();
+        self
+    }
+
+    /// Sets the base URL that thumbnails are served from.
+    pub fn cdn_url(mut self, url: impl Into<String>) -> Self {
+        self.cdn_url = url.into();
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets a timeout covering the whole of each request, from connecting
+    /// until the response body has been read.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets headers sent with every request. These don't override the
+    /// User-Agent; use `user_agent` for that.
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers = headers;
+        self
+    }
+
+    /// Sets how failed requests are retried. Defaults to
+    /// `RetryPolicy::default()`; use `RetryPolicy::none()` to disable retries.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Sets the limit for requests to the API host. Defaults to 10 requests
+    /// per second with at most 8 in flight.
+    pub fn api_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.api_rate_limit = limit;
+        self
+    }
+
+    /// Sets the limit for requests to any host other than the API, such as
+    /// the CDN and file downloads. Unlimited by default.
+    pub fn cdn_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.cdn_rate_limit = limit;
+        self
+    }
+
+    /// Sets the limit for requests to one host, overriding the API or CDN
+    /// limit for it.
+    pub fn host_rate_limit(mut self, host: impl Into<String>, limit: RateLimit) -> Self {
+        self.host_rate_limits.insert(host.into(), limit);
+        self
+    }
+
+    /// Sets how data that doesn't match the expected schema is handled.
+    /// Defaults to `ParseMode::Lenient`.
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    /// Caches API responses on disk. File downloads are never cached.
+    pub fn cache(mut self, cache: DiskCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let mut http = reqwest::Client::builder();
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            http = http.connect_timeout(timeout);
+        }
+        let transport = ReqwestTransport::new(http.build()?);
+        self.build_with_transport(transport)
+    }
+
+    /// Builds a client that sends requests through `transport`. Timeouts are
+    /// the transport's responsibility, so `timeout` and `connect_timeout` are
+    /// ignored.
+    pub fn build_with_transport<T: Transport>(self, transport: T) -> Result<Client<T>> {
+        let headers = self.headers()?;
+        let endpoints = self.endpoints()?;
+        let throttle = Throttle::new(&endpoints.api_url, self.api_rate_limit, self.cdn_rate_limit, self.host_rate_limits);
+        let client = Client {
+            transport,
+            headers,
+            endpoints,
+            retry: self.retry,
+            throttle: Arc::new(throttle),
+            cache: self.cache.map(Arc::new)
+        };
+        log::debug!("Built PolyHaven client for {} with {:?}", client.api_url(), client.retry);
+        Ok(client)
+    }
+
+    /// Builds a client that answers every query from `snapshot` instead of
+    /// the network, as if it were the API at the configured `api_url`.
+    ///
+    /// Rate limits, retries and the cache don't apply, since no requests
+    /// leave the process.
+    pub fn build_offline(self, snapshot: Snapshot) -> Result<Client<SnapshotTransport>> {
+        let transport = SnapshotTransport::new(snapshot, self.endpoints()?.api_url);
+        Self {
+            retry: RetryPolicy::none(),
+            api_rate_limit: RateLimit::unlimited(),
+            cdn_rate_limit: RateLimit::unlimited(),
+            host_rate_limits: HashMap::new(),
+            cache: None,
+            ..self
+        }.build_with_transport(transport)
+    }
+
+    /// The headers to send with every request, including the User-Agent.
+    pub(crate) fn headers(&self) -> Result<HeaderMap> {
+        let mut headers = self.default_headers.clone();
+        headers.insert(USER_AGENT, HeaderValue::from_str(&self.user_agent)?);
+        Ok(headers)
+    }
+
+    pub(crate) fn endpoints(&self) -> Result<Endpoints> {
+        Endpoints::new(&self.api_url, &self.cdn_url, self.parse_mode)
+    }
+
+    /// Builds a `BlockingClient` with this configuration instead. The blocking
+    /// client doesn't support caching, so any `cache` is ignored.
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking(self) -> Result<crate::blocking::BlockingClient> {
+        let mut http = reqwest::blocking::Client::builder().default_headers(self.headers()?);
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            http = http.connect_timeout(timeout);
+        }
+
+        let endpoints = self.endpoints()?;
+        let throttle = Throttle::new(&endpoints.api_url, self.api_rate_limit, self.cdn_rate_limit, self.host_rate_limits);
+        let client = crate::blocking::BlockingClient {
+            http: http.build()?,
+            endpoints,
+            retry: self.retry,
+            throttle: Arc::new(throttle)
+        };
+        log::debug!("Built blocking PolyHaven client for {} with {:?}", client.api_url(), client.retry);
+        Ok(client)
+    }
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,8 +1,11 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, str::FromStr};
 
 use chrono::{DateTime, Utc};
 
-#[derive(Debug)]
+use crate::{Error, DEFAULT_CDN_URL};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AssetInfo {
     pub id: String,
     pub name: String,
@@ -18,18 +21,61 @@ pub struct AssetInfo {
 
 impl AssetInfo {
     pub fn thumbnail(&self, resolution: u32) -> String {
-        format!("https://cdn.polyhaven.com/asset_img/thumbs/{}.png?height={}", self.id, resolution)
+        self.thumbnail_on(DEFAULT_CDN_URL, resolution)
+    }
+
+    /// The URL of this asset's thumbnail on the given CDN. Prefer
+    /// `Client::thumbnail`, which uses the client's configured CDN.
+    pub fn thumbnail_on(&self, cdn_url: &str, resolution: u32) -> String {
+        format!("{}/asset_img/thumbs/{}.png?height={}", cdn_url, self.id, resolution)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum AssetType {
     HDRI,
     Texture,
     Model
 }
 
-#[derive(Debug)]
+impl AssetType {
+    /// The plural name the API uses for this type in URLs, e.g. `"hdris"`.
+    pub fn api_name(&self) -> &'static str {
+        match self {
+            Self::HDRI => "hdris",
+            Self::Texture => "textures",
+            Self::Model => "models"
+        }
+    }
+}
+
+/// Writes the type's `api_name`.
+impl fmt::Display for AssetType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.api_name())
+    }
+}
+
+/// Parses an `api_name` or its singular, ignoring case, e.g. `"hdris"` or
+/// `"Texture"`.
+impl FromStr for AssetType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hdri" | "hdris" => Ok(Self::HDRI),
+            "texture" | "textures" => Ok(Self::Texture),
+            "model" | "models" => Ok(Self::Model),
+            _ => Err(Error::InvalidAssetType(s.to_string()))
+        }
+    }
+}
+
+serde_as_string!(AssetType);
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "lowercase"))]
 pub enum Asset {
     HDRI(HDRIAsset),
     Texture(TextureAsset),
@@ -37,7 +83,20 @@ pub enum Asset {
     Unparsed
 }
 
-#[derive(Debug)]
+impl Asset {
+    /// The type of this asset, or `None` if it couldn't be parsed.
+    pub fn asset_type(&self) -> Option<AssetType> {
+        match self {
+            Self::HDRI(_) => Some(AssetType::HDRI),
+            Self::Texture(_) => Some(AssetType::Texture),
+            Self::Model(_) => Some(AssetType::Model),
+            Self::Unparsed => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HDRIAsset {
     pub whitebalance: Option<u32>,
     pub backplates: bool,
@@ -45,11 +104,13 @@ pub struct HDRIAsset {
     pub coords: Option<(f32, f32)>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextureAsset {
     pub dimensions: (f32, f32)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModelAsset;
 
@@ -1,6 +1,9 @@
-use std::{collections::HashMap, str::FromStr, convert::Infallible};
+use std::{collections::HashMap, str::FromStr, convert::Infallible, fmt};
 
-#[derive(Debug)]
+use crate::{data::asset::AssetType, Error};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FileData {
     pub url: String,
     pub md5: String,
@@ -8,24 +11,93 @@ pub struct FileData {
     pub include: HashMap<String, FileData>,
 }
 
-type FileResolution = u64;
+/// The resolution of a file, by its nominal pixel width.
+///
+/// The API labels resolutions like `"4k"`, meaning 4096 pixels wide, which is
+/// how they are displayed and serialized. Widths that aren't a whole number
+/// of `k` are written as plain numbers, like `"1500"`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Resolution(u32);
+
+impl Resolution {
+    pub const fn from_pixels(width: u32) -> Self {
+        Self(width)
+    }
+
+    /// A resolution of `k` times 1024 pixels, e.g. `Resolution::from_k(4)`
+    /// for `4k`, or `None` if that's too many pixels for a `u32`.
+    pub const fn from_k(k: u32) -> Option<Self> {
+        match k.checked_mul(1024) {
+            Some(width) => Some(Self(width)),
+            None => None
+        }
+    }
+
+    /// The nominal pixel width, e.g. 4096 for `4k`.
+    pub fn pixels(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 != 0 && self.0 % 1024 == 0 {
+            write!(f, "{}k", self.0 / 1024)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
 
-#[derive(Debug)]
+/// Parses a label like `"4k"` or `"4K"`, or a pixel width like `"4096"`.
+impl FromStr for Resolution {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let resolution = match trimmed.strip_suffix(['k', 'K']) {
+            Some(k) => k.parse().ok().and_then(Self::from_k),
+            None => trimmed.parse().ok().map(Self::from_pixels)
+        };
+        resolution.ok_or_else(|| Error::InvalidResolution(s.to_string()))
+    }
+}
+
+serde_as_string!(Resolution);
+
+/// The files of each texture map, by resolution and then format.
+pub type TextureMaps = HashMap<TextureMap, HashMap<Resolution, HashMap<TextureFormat, FileData>>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Files {
     HDRI(HDRIFiles),
     Texture(TextureFiles),
     Model(ModelFiles)
 }
 
-#[derive(Debug)]
+impl Files {
+    /// The type of asset these are the files of.
+    pub fn asset_type(&self) -> AssetType {
+        match self {
+            Self::HDRI(_) => AssetType::HDRI,
+            Self::Texture(_) => AssetType::Texture,
+            Self::Model(_) => AssetType::Model
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HDRIFiles {
-    pub hdri: HashMap<FileResolution, HashMap<HDRIFormat, FileData>>,
+    pub hdri: HashMap<Resolution, HashMap<HDRIFormat, FileData>>,
     pub backplates: HashMap<String, HashMap<HDRIBackplateFormat, FileData>>,
     pub colorchart: Option<FileData>,
     pub tonemapped: Option<FileData>
 } 
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum HDRIFormat {
     Hdr,
     Exr,
@@ -44,7 +116,19 @@ impl FromStr for HDRIFormat {
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+impl fmt::Display for HDRIFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hdr => "hdr",
+            Self::Exr => "exr",
+            Self::Unparsed(name) => name
+        })
+    }
+}
+
+serde_as_string!(HDRIFormat);
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum HDRIBackplateFormat {
     JpgPretty,
     JpgPlain,
@@ -65,14 +149,28 @@ impl FromStr for HDRIBackplateFormat {
     }
 }
 
-#[derive(Debug)]
+impl fmt::Display for HDRIBackplateFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::JpgPretty => "jpg_pretty",
+            Self::JpgPlain => "jpg_plain",
+            Self::Raw => "raw",
+            Self::Unparsed(name) => name
+        })
+    }
+}
+
+serde_as_string!(HDRIBackplateFormat);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextureFiles {
-    pub blend: HashMap<FileResolution, FileData>,
-    pub gltf: HashMap<FileResolution, FileData>,
-    pub maps: HashMap<TextureMap, HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
+    pub blend: HashMap<Resolution, FileData>,
+    pub gltf: HashMap<Resolution, FileData>,
+    pub maps: TextureMaps,
 }
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum TextureMap {
     AO,
     ARM,
@@ -81,8 +179,14 @@ pub enum TextureMap {
     Displacement,
     Metal,
     NorGL,
+    NorDX,
     Rough,
     Spec,
+    Mask,
+    Translucency,
+    Emission,
+    Opacity,
+    /// A map this crate doesn't know about, keyed by its name in the API.
     Unparsed(String)
 }
 
@@ -98,14 +202,43 @@ impl FromStr for TextureMap {
             "displacement" => Self::Displacement,
             "metal" => Self::Metal,
             "nor_gl" => Self::NorGL,
+            "nor_dx" => Self::NorDX,
             "rough" => Self::Rough,
             "spec" => Self::Spec,
+            "mask" => Self::Mask,
+            "translucency" => Self::Translucency,
+            "emission" => Self::Emission,
+            "opacity" => Self::Opacity,
             _ => Self::Unparsed(s.to_string())
         })
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+impl fmt::Display for TextureMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::AO => "ao",
+            Self::ARM => "arm",
+            Self::Bump => "bump",
+            Self::Diffuse => "diffuse",
+            Self::Displacement => "displacement",
+            Self::Metal => "metal",
+            Self::NorGL => "nor_gl",
+            Self::NorDX => "nor_dx",
+            Self::Rough => "rough",
+            Self::Spec => "spec",
+            Self::Mask => "mask",
+            Self::Translucency => "translucency",
+            Self::Emission => "emission",
+            Self::Opacity => "opacity",
+            Self::Unparsed(name) => name
+        })
+    }
+}
+
+serde_as_string!(TextureMap);
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum TextureFormat {
     Exr,
     Jpg,
@@ -126,10 +259,24 @@ impl FromStr for TextureFormat {
     }
 }
 
-#[derive(Debug)]
+impl fmt::Display for TextureFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Exr => "exr",
+            Self::Jpg => "jpg",
+            Self::Png => "png",
+            Self::Unparsed(name) => name
+        })
+    }
+}
+
+serde_as_string!(TextureFormat);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModelFiles {
-    pub blend: HashMap<FileResolution, FileData>,
-    pub gltf: HashMap<FileResolution, FileData>,
-    pub fbx: HashMap<FileResolution, FileData>,
-    pub maps: HashMap<TextureMap, HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
+    pub blend: HashMap<Resolution, FileData>,
+    pub gltf: HashMap<Resolution, FileData>,
+    pub fbx: HashMap<Resolution, FileData>,
+    pub maps: TextureMaps,
 }
\ No newline at end of file
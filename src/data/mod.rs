@@ -1,3 +1,52 @@
+//! The types returned by the API, converted from its JSON into something
+//! easier to work with.
+//!
+//! With the `serde` feature, every type here implements `Serialize` and
+//! `Deserialize` with a stable representation:
+//!
+//! - structs are objects with the same field names as in Rust;
+//! - `AssetType`, `Resolution`, `TextureMap`, `TextureFormat`, `HDRIFormat`
+//!   and `HDRIBackplateFormat` are the strings given by their `Display`
+//!   impls, which are the names the API uses, like `"hdris"`, `"4k"`,
+//!   `"nor_gl"` or `"exr"`. Unparsed values are written as the name they had
+//!   in the API, so they round-trip too. The same strings are used when these
+//!   types are map keys;
+//! - `Asset` is an object tagged with a lowercase `"type"`, like
+//!   `{ "type": "hdri", "evs_cap": 12, ... }`;
+//! - `Files` is an object with a single lowercase key for its type, like
+//!   `{ "texture": { "blend": ..., ... } }`;
+//! - dates are RFC 3339 strings.
+//!
+//! Note that asset types are named two ways on purpose. `AssetType` uses the
+//! API's plural type names, `"hdris"`, `"textures"` and `"models"`, as in
+//! `/assets?type=hdris`, while the `Asset` tag and `Files` key are the
+//! singular variant names, `"hdri"`, `"texture"` and `"model"`. Deserializing
+//! an `AssetType` accepts either.
+
+/// Implements `Serialize` with `Display` and `Deserialize` with `FromStr`,
+/// for types that are written as plain strings.
+macro_rules! serde_as_string {
+    ($($ty:ty),*) => {
+        $(
+            #[cfg(feature = "serde")]
+            impl serde::Serialize for $ty {
+                fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            #[cfg(feature = "serde")]
+            impl<'de> serde::Deserialize<'de> for $ty {
+                fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+                    value.parse().map_err(serde::de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
 pub mod asset;
 pub mod author;
-pub mod files;
\ No newline at end of file
+pub mod files;
+pub mod select;
\ No newline at end of file
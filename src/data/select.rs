@@ -0,0 +1,333 @@
+//! Choosing files by resolution and format.
+//!
+//! Every selection takes a target `Resolution`, a `ResolutionPolicy` for
+//! when that exact resolution isn't available, and an ordered format
+//! preference. Resolutions are chosen first, from those that have at least
+//! one acceptable format; the most preferred format at that resolution is
+//! then used. Formats that aren't in the preference are never chosen.
+
+use std::{collections::HashMap, hash::Hash};
+
+use super::files::{FileData, HDRIFiles, HDRIFormat, ModelFiles, Resolution, TextureFiles, TextureFormat, TextureMap, TextureMaps};
+
+/// How to choose a resolution when the target isn't available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResolutionPolicy {
+    /// Only the target resolution.
+    Exact,
+    /// The available resolution closest to the target, preferring the
+    /// smaller one if two are equally close.
+    Nearest,
+    /// The largest available resolution no bigger than the target.
+    AtMost,
+    /// The smallest available resolution no smaller than the target.
+    AtLeast
+}
+
+impl ResolutionPolicy {
+    /// Chooses from `available` according to this policy.
+    pub fn choose(self, target: Resolution, available: impl IntoIterator<Item = Resolution>) -> Option<Resolution> {
+        let mut available = available.into_iter();
+        match self {
+            Self::Exact => available.find(|res| *res == target),
+            Self::Nearest => available.min_by_key(|res| (res.pixels().abs_diff(target.pixels()), *res)),
+            Self::AtMost => available.filter(|res| *res <= target).max(),
+            Self::AtLeast => available.filter(|res| *res >= target).min()
+        }
+    }
+
+    /// Chooses a file from files keyed by resolution, such as
+    /// `ModelFiles::gltf`.
+    pub fn select(self, target: Resolution, files: &HashMap<Resolution, FileData>) -> Option<(Resolution, &FileData)> {
+        let resolution = self.choose(target, files.keys().copied())?;
+        Some((resolution, &files[&resolution]))
+    }
+}
+
+/// A file chosen by resolution and format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selected<'a, F> {
+    pub resolution: Resolution,
+    pub format: &'a F,
+    pub file: &'a FileData
+}
+
+/// The files for a set of texture maps, chosen for one target resolution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapSet<'a> {
+    /// The resolution that was asked for.
+    pub resolution: Resolution,
+    /// The chosen file for each map that had one.
+    pub maps: HashMap<&'a TextureMap, Selected<'a, TextureFormat>>,
+    /// The maps in `maps` that aren't at the target resolution or aren't in
+    /// the most preferred format.
+    pub fallbacks: Vec<Fallback<'a>>,
+    /// The requested maps that have no file allowed by the policy and format
+    /// preference.
+    pub missing: Vec<TextureMap>
+}
+
+impl MapSet<'_> {
+    /// Whether every requested map has a file.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// A map in a `MapSet` that had to fall back to another resolution or a less
+/// preferred format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fallback<'a> {
+    pub map: &'a TextureMap,
+    pub resolution: Resolution,
+    pub format: &'a TextureFormat
+}
+
+impl HDRIFiles {
+    /// The resolutions the HDRI is available in, smallest first.
+    pub fn resolutions(&self) -> Vec<Resolution> {
+        sorted_keys(&self.hdri)
+    }
+
+    /// The formats the HDRI is available in at `resolution`.
+    pub fn formats(&self, resolution: Resolution) -> Vec<&HDRIFormat> {
+        self.hdri.get(&resolution).map(|formats| formats.keys().collect()).unwrap_or_default()
+    }
+
+    /// Chooses the HDRI file for `target`, in the first of `formats` that is
+    /// available.
+    pub fn select(&self, target: Resolution, policy: ResolutionPolicy, formats: &[HDRIFormat]) -> Option<Selected<'_, HDRIFormat>> {
+        select_from(&self.hdri, target, policy, formats)
+    }
+}
+
+impl TextureFiles {
+    /// The texture maps available, in no particular order.
+    pub fn map_names(&self) -> Vec<&TextureMap> {
+        self.maps.keys().collect()
+    }
+
+    /// The resolutions `map` is available in, smallest first.
+    pub fn resolutions(&self, map: &TextureMap) -> Vec<Resolution> {
+        map_resolutions(&self.maps, map)
+    }
+
+    /// The formats `map` is available in at `resolution`.
+    pub fn formats(&self, map: &TextureMap, resolution: Resolution) -> Vec<&TextureFormat> {
+        map_formats(&self.maps, map, resolution)
+    }
+
+    /// Chooses the file for `map` at `target`, in the first of `formats` that
+    /// is available.
+    pub fn select(&self, map: &TextureMap, target: Resolution, policy: ResolutionPolicy, formats: &[TextureFormat]) -> Option<Selected<'_, TextureFormat>> {
+        select_from(self.maps.get(map)?, target, policy, formats)
+    }
+
+    /// Chooses files for each of `maps` at `target`, or for every map if
+    /// `maps` is empty.
+    pub fn map_set(&self, maps: &[TextureMap], target: Resolution, policy: ResolutionPolicy, formats: &[TextureFormat]) -> MapSet<'_> {
+        map_set(&self.maps, maps, target, policy, formats)
+    }
+}
+
+impl ModelFiles {
+    /// The texture maps available, in no particular order.
+    pub fn map_names(&self) -> Vec<&TextureMap> {
+        self.maps.keys().collect()
+    }
+
+    /// The resolutions `map` is available in, smallest first.
+    pub fn resolutions(&self, map: &TextureMap) -> Vec<Resolution> {
+        map_resolutions(&self.maps, map)
+    }
+
+    /// The formats `map` is available in at `resolution`.
+    pub fn formats(&self, map: &TextureMap, resolution: Resolution) -> Vec<&TextureFormat> {
+        map_formats(&self.maps, map, resolution)
+    }
+
+    /// Chooses the file for `map` at `target`, in the first of `formats` that
+    /// is available.
+    pub fn select(&self, map: &TextureMap, target: Resolution, policy: ResolutionPolicy, formats: &[TextureFormat]) -> Option<Selected<'_, TextureFormat>> {
+        select_from(self.maps.get(map)?, target, policy, formats)
+    }
+
+    /// Chooses files for each of `maps` at `target`, or for every map if
+    /// `maps` is empty.
+    pub fn map_set(&self, maps: &[TextureMap], target: Resolution, policy: ResolutionPolicy, formats: &[TextureFormat]) -> MapSet<'_> {
+        map_set(&self.maps, maps, target, policy, formats)
+    }
+}
+
+fn sorted_keys<V>(files: &HashMap<Resolution, V>) -> Vec<Resolution> {
+    let mut resolutions = files.keys().copied().collect::<Vec<_>>();
+    resolutions.sort();
+    resolutions
+}
+
+fn map_resolutions(maps: &TextureMaps, map: &TextureMap) -> Vec<Resolution> {
+    maps.get(map).map(sorted_keys).unwrap_or_default()
+}
+
+fn map_formats<'a>(maps: &'a TextureMaps, map: &TextureMap, resolution: Resolution) -> Vec<&'a TextureFormat> {
+    maps.get(map)
+        .and_then(|resolutions| resolutions.get(&resolution))
+        .map(|formats| formats.keys().collect())
+        .unwrap_or_default()
+}
+
+/// The first of `preference` that is in `formats`.
+fn preferred<'a, F: Eq + Hash>(formats: &'a HashMap<F, FileData>, preference: &[F]) -> Option<(&'a F, &'a FileData)> {
+    preference.iter().find_map(|format| formats.get_key_value(format))
+}
+
+fn select_from<'a, F: Eq + Hash>(
+    files: &'a HashMap<Resolution, HashMap<F, FileData>>,
+    target: Resolution,
+    policy: ResolutionPolicy,
+    preference: &[F]
+) -> Option<Selected<'a, F>> {
+    let acceptable = files.iter()
+        .filter(|(_, formats)| preferred(formats, preference).is_some())
+        .map(|(resolution, _)| *resolution);
+    let resolution = policy.choose(target, acceptable)?;
+    let (format, file) = preferred(&files[&resolution], preference)?;
+    Some(Selected { resolution, format, file })
+}
+
+fn map_set<'a>(
+    maps: &'a TextureMaps,
+    wanted: &[TextureMap],
+    target: Resolution,
+    policy: ResolutionPolicy,
+    preference: &[TextureFormat]
+) -> MapSet<'a> {
+    let mut set = MapSet {
+        resolution: target,
+        maps: HashMap::new(),
+        fallbacks: Vec::new(),
+        missing: Vec::new()
+    };
+    let wanted = match wanted {
+        [] => maps.keys().cloned().collect(),
+        wanted => wanted.to_vec()
+    };
+    for map in wanted {
+        let Some((map, resolutions)) = maps.get_key_value(&map) else {
+            set.missing.push(map);
+            continue;
+        };
+        let Some(selected) = select_from(resolutions, target, policy, preference) else {
+            set.missing.push(map.clone());
+            continue;
+        };
+        if selected.resolution != target || preference.first() != Some(selected.format) {
+            set.fallbacks.push(Fallback { map, resolution: selected.resolution, format: selected.format });
+        }
+        set.maps.insert(map, selected);
+    }
+    set
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn res(label: &str) -> Resolution {
+        label.parse().unwrap()
+    }
+
+    fn file(name: &str) -> FileData {
+        FileData { url: format!("https://dl.polyhaven.org/{}", name), md5: String::new(), size: 0, include: HashMap::new() }
+    }
+
+    /// An HDRI in hdr and exr at 1k, only exr at 4k and only hdr at 8k.
+    fn hdri() -> HDRIFiles {
+        let formats = |formats: &[HDRIFormat]| formats.iter()
+            .map(|format| (format.clone(), file(&format.to_string())))
+            .collect();
+        HDRIFiles {
+            hdri: HashMap::from([
+                (res("1k"), formats(&[HDRIFormat::Hdr, HDRIFormat::Exr])),
+                (res("4k"), formats(&[HDRIFormat::Exr])),
+                (res("8k"), formats(&[HDRIFormat::Hdr]))
+            ]),
+            backplates: HashMap::new(),
+            colorchart: None,
+            tonemapped: None
+        }
+    }
+
+    fn choose(policy: ResolutionPolicy, target: &str, available: &[&str]) -> Option<Resolution> {
+        policy.choose(res(target), available.iter().map(|label| res(label)))
+    }
+
+    #[test]
+    fn exact_only_chooses_the_target() {
+        assert_eq!(choose(ResolutionPolicy::Exact, "4k", &["1k", "4k", "8k"]), Some(res("4k")));
+        assert_eq!(choose(ResolutionPolicy::Exact, "2k", &["1k", "4k", "8k"]), None);
+    }
+
+    #[test]
+    fn nearest_prefers_the_smaller_of_a_tie() {
+        assert_eq!(choose(ResolutionPolicy::Nearest, "2k", &["1k", "4k", "8k"]), Some(res("1k")));
+        assert_eq!(choose(ResolutionPolicy::Nearest, "6k", &["1k", "4k", "8k"]), Some(res("4k")));
+        assert_eq!(choose(ResolutionPolicy::Nearest, "16k", &["1k", "4k", "8k"]), Some(res("8k")));
+    }
+
+    #[test]
+    fn at_most_and_at_least_stay_on_their_side_of_the_target() {
+        assert_eq!(choose(ResolutionPolicy::AtMost, "6k", &["1k", "4k", "8k"]), Some(res("4k")));
+        assert_eq!(choose(ResolutionPolicy::AtMost, "512", &["1k", "4k", "8k"]), None);
+        assert_eq!(choose(ResolutionPolicy::AtLeast, "2k", &["1k", "4k", "8k"]), Some(res("4k")));
+        assert_eq!(choose(ResolutionPolicy::AtLeast, "16k", &["1k", "4k", "8k"]), None);
+    }
+
+    #[test]
+    fn nothing_is_chosen_from_nothing() {
+        for policy in [ResolutionPolicy::Exact, ResolutionPolicy::Nearest, ResolutionPolicy::AtMost, ResolutionPolicy::AtLeast] {
+            assert_eq!(choose(policy, "1k", &[]), None);
+        }
+        let empty = HDRIFiles { hdri: HashMap::new(), ..hdri() };
+        assert_eq!(empty.select(res("1k"), ResolutionPolicy::Nearest, &[HDRIFormat::Hdr]), None);
+        assert!(empty.resolutions().is_empty());
+    }
+
+    #[test]
+    fn most_preferred_format_is_chosen() {
+        let hdri = hdri();
+        let selected = hdri.select(res("1k"), ResolutionPolicy::Exact, &[HDRIFormat::Exr, HDRIFormat::Hdr]).unwrap();
+        assert_eq!(selected.format, &HDRIFormat::Exr);
+        assert_eq!(selected.file.url, "https://dl.polyhaven.org/exr");
+    }
+
+    #[test]
+    fn resolutions_without_an_acceptable_format_are_skipped() {
+        let hdri = hdri();
+        assert_eq!(hdri.select(res("4k"), ResolutionPolicy::Exact, &[HDRIFormat::Hdr]), None);
+        // 4k is closest, but only has exr.
+        let selected = hdri.select(res("5k"), ResolutionPolicy::Nearest, &[HDRIFormat::Hdr]).unwrap();
+        assert_eq!((selected.resolution, selected.format), (res("8k"), &HDRIFormat::Hdr));
+        assert_eq!(hdri.select(res("1k"), ResolutionPolicy::Nearest, &[]), None);
+    }
+
+    #[test]
+    fn map_set_reports_missing_maps_and_fallbacks() {
+        let formats = HashMap::from([(TextureFormat::Png, file("png"))]);
+        let maps = HashMap::from([
+            (TextureMap::Diffuse, HashMap::from([(res("1k"), formats.clone()), (res("2k"), formats.clone())])),
+            (TextureMap::Rough, HashMap::from([(res("1k"), formats)]))
+        ]);
+        let wanted = [TextureMap::Diffuse, TextureMap::Rough, TextureMap::AO];
+        let set = map_set(&maps, &wanted, res("2k"), ResolutionPolicy::AtMost, &[TextureFormat::Png]);
+
+        assert_eq!(set.maps[&TextureMap::Diffuse].resolution, res("2k"));
+        assert_eq!(set.fallbacks, [Fallback { map: &TextureMap::Rough, resolution: res("1k"), format: &TextureFormat::Png }]);
+        assert_eq!(set.missing, [TextureMap::AO]);
+        assert!(!set.is_complete());
+
+        let empty = TextureMaps::new();
+        assert!(map_set(&empty, &[], res("2k"), ResolutionPolicy::Nearest, &[TextureFormat::Png]).is_complete());
+        assert_eq!(map_set(&empty, &[TextureMap::AO], res("2k"), ResolutionPolicy::Nearest, &[TextureFormat::Png]).missing, [TextureMap::AO]);
+    }
+}
\ No newline at end of file
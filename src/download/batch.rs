@@ -0,0 +1,192 @@
+use std::{collections::HashMap, fmt, path::{Path, PathBuf}, sync::Arc};
+
+use futures_util::{stream, StreamExt};
+
+use crate::{data::files::FileData, throttle::TokenBucket, transport::Transport, Client, Error};
+
+use super::{part_path, progress::Tracker, Download, ProgressObserver};
+
+/// Many downloads run together, started with `Client::download_batch`.
+///
+/// Jobs with the same MD5 are only downloaded once; the file is then copied
+/// to the other jobs' destinations. Each destination is only written by the
+/// first job with it: later jobs for the same file share its result, and
+/// jobs for a different file fail with `Error::DuplicateDestination`. A
+/// failed job doesn't stop the others, so `run` always returns a report of
+/// every job.
+pub struct Batch<'a, T> {
+    client: &'a Client<T>,
+    jobs: Vec<(FileData, PathBuf)>,
+    concurrency: usize,
+    bytes_per_second: Option<u64>,
+    observer: Option<Arc<dyn ProgressObserver>>
+}
+
+/// How a job in a batch got its file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobOutcome {
+    Downloaded,
+    /// Copied from another job's destination, which had the same file.
+    Copied {
+        from: PathBuf
+    }
+}
+
+/// The result of one job in a batch.
+#[derive(Debug)]
+pub struct JobResult {
+    pub file: FileData,
+    pub destination: PathBuf,
+    /// Jobs that would have been copied from a failed download share its
+    /// error, so errors are reference counted.
+    pub result: Result<JobOutcome, Arc<Error>>
+}
+
+/// The results of every job in a batch, in the order they were given.
+#[derive(Debug)]
+pub struct BatchReport {
+    pub jobs: Vec<JobResult>
+}
+
+impl BatchReport {
+    /// Whether every job succeeded.
+    pub fn is_success(&self) -> bool {
+        self.jobs.iter().all(|job| job.result.is_ok())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &JobResult> {
+        self.jobs.iter().filter(|job| job.result.is_err())
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// Starts a batch download of `(file, destination)` jobs. Each file is
+    /// downloaded as with `Download::to_path`.
+    pub fn download_batch(&self, jobs: impl IntoIterator<Item = (FileData, PathBuf)>) -> Batch<'_, T> {
+        Batch {
+            client: self,
+            jobs: jobs.into_iter().collect(),
+            concurrency: 4,
+            bytes_per_second: None,
+            observer: None
+        }
+    }
+}
+
+impl<T: Transport> Batch<'_, T> {
+    /// Sets how many files are downloaded at once. Defaults to 4.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Limits the combined speed of every download in the batch. Unlimited
+    /// by default.
+    pub fn max_bytes_per_second(mut self, bytes_per_second: u64) -> Self {
+        self.bytes_per_second = Some(bytes_per_second);
+        self
+    }
+
+    /// Reports the progress of every download to `observer`. Files copied
+    /// from another job aren't reported, since nothing is downloaded for them.
+    pub fn progress(mut self, observer: impl ProgressObserver + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    pub async fn run(self) -> BatchReport {
+        // The first job with each MD5 downloads it, and the rest copy it.
+        // Jobs whose destination an earlier job already has are left out.
+        let mut primaries = Vec::new();
+        let mut primary_of = Vec::with_capacity(self.jobs.len());
+        let mut duplicate_of = Vec::with_capacity(self.jobs.len());
+        let mut by_md5 = HashMap::new();
+        let mut by_destination = HashMap::new();
+        for (index, (file, destination)) in self.jobs.iter().enumerate() {
+            if let Some(first) = by_destination.get(destination) {
+                duplicate_of.push(Some(*first));
+                primary_of.push(index);
+                continue;
+            }
+            by_destination.insert(destination, index);
+            duplicate_of.push(None);
+            let primary = *by_md5.entry(file.md5.to_lowercase()).or_insert_with(|| {
+                primaries.push(index);
+                index
+            });
+            primary_of.push(primary);
+        }
+
+        let bandwidth = self.bytes_per_second.map(|rate| {
+            let rate = rate.max(1) as f64;
+            Arc::new(TokenBucket::new(rate, rate))
+        });
+        let tracker = self.observer.as_ref().map(|observer| {
+            Arc::new(Tracker::new(observer.clone(), primaries.iter().map(|index| &self.jobs[*index].0)))
+        });
+        let mut downloaded = stream::iter(primaries)
+            .map(|index| {
+                let (file, destination) = &self.jobs[index];
+                let download = Download {
+                    client: self.client,
+                    file,
+                    observer: None,
+                    tracker: tracker.clone(),
+                    bandwidth: bandwidth.clone()
+                };
+                async move { (index, download.to_path(destination).await.map_err(Arc::new)) }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect::<HashMap<_, _>>()
+            .await;
+
+        let mut jobs: Vec<JobResult> = Vec::with_capacity(self.jobs.len());
+        for (index, (file, destination)) in self.jobs.iter().enumerate() {
+            let primary = primary_of[index];
+            let result = if let Some(first) = duplicate_of[index] {
+                match self.jobs[first].0.md5.eq_ignore_ascii_case(&file.md5) {
+                    true => jobs[first].result.clone(),
+                    false => Err(Arc::new(Error::DuplicateDestination { path: destination.clone() }))
+                }
+            } else if primary == index {
+                downloaded.remove(&index).expect("every primary job is downloaded").map(|()| JobOutcome::Downloaded)
+            } else {
+                let from = &self.jobs[primary].1;
+                match jobs.get(primary).map(|job| &job.result) {
+                    Some(Err(err)) => Err(err.clone()),
+                    _ => copy(from, destination).await.map(|()| JobOutcome::Copied { from: from.clone() }).map_err(Arc::new)
+                }
+            };
+            jobs.push(JobResult {
+                file: file.clone(),
+                destination: destination.clone(),
+                result
+            });
+        }
+        BatchReport { jobs }
+    }
+}
+
+/// Copies a downloaded file to another destination, going through a `.part`
+/// file so that `to` never holds a partial copy.
+async fn copy(from: &Path, to: &Path) -> Result<(), Error> {
+    if from == to {
+        return Ok(());
+    }
+    if let Some(parent) = to.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|err| Error::io(parent, err))?;
+    }
+    let part = part_path(to);
+    tokio::fs::copy(from, &part).await.map_err(|err| Error::io(&part, err))?;
+    tokio::fs::rename(&part, to).await.map_err(|err| Error::io(to, err))
+}
+
+impl<T> fmt::Debug for Batch<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batch")
+            .field("jobs", &self.jobs.len())
+            .field("concurrency", &self.concurrency)
+            .field("bytes_per_second", &self.bytes_per_second)
+            .finish_non_exhaustive()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,427 @@
+//! Downloading asset files.
+//!
+//! Every download streams the file's body, so large files are never held in
+//! memory, and checks it against the size and MD5 that the API gave for it.
+
+use std::{fmt, io, path::{Path, PathBuf}, sync::Arc};
+
+use md5::{Digest, Md5};
+use percent_encoding::percent_decode_str;
+use reqwest::{header::{HeaderMap, HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, RANGE}, StatusCode};
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use url::Url;
+
+use crate::{data::files::FileData, throttle::{Permit, TokenBucket}, transport::{HttpResponse, Transport, TransportError, TransportErrorKind}, Client, Error, Result};
+
+use self::progress::Tracker;
+
+mod batch;
+mod progress;
+
+pub use self::batch::{Batch, BatchReport, JobOutcome, JobResult};
+pub use self::progress::{DownloadEvent, Progress, ProgressObserver};
+
+/// A download of one file, started with `Client::download`. Nothing is
+/// fetched until it's given a destination.
+pub struct Download<'a, T> {
+    client: &'a Client<T>,
+    file: &'a FileData,
+    observer: Option<Arc<dyn ProgressObserver>>,
+    /// Set when this download is part of a larger one, which reports progress
+    /// for all of its files together.
+    tracker: Option<Arc<Tracker>>,
+    /// Shared by every download in a batch with a bandwidth limit.
+    bandwidth: Option<Arc<TokenBucket>>
+}
+
+impl<T: Transport> Client<T> {
+    /// Starts a download of `file`.
+    pub fn download<'a>(&'a self, file: &'a FileData) -> Download<'a, T> {
+        Download {
+            client: self,
+            file,
+            observer: None,
+            tracker: None,
+            bandwidth: None
+        }
+    }
+}
+
+impl<T: Transport> Download<'_, T> {
+    /// Reports the download's progress to `observer`. For `to_dir`, the
+    /// overall progress covers every included file.
+    pub fn progress(mut self, observer: impl ProgressObserver + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Downloads the file to `path`, creating its parent directories.
+    ///
+    /// The file is written to a `.part` file next to `path` and only renamed
+    /// into place once it has been verified, so `path` never holds a partial
+    /// or corrupt download. Failed attempts are retried according to the
+    /// client's `RetryPolicy`.
+    ///
+    /// If the download fails part way through, the `.part` file is kept, and
+    /// the next attempt, or a later download to the same path, resumes from
+    /// it with a `Range` request. If the server doesn't support ranges, or
+    /// the resumed file fails verification, the download starts again from
+    /// scratch.
+    pub async fn to_path(self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|err| Error::io(parent, err))?;
+        }
+        let part = part_path(path);
+        let tracker = self.tracker(&[self.file]);
+        let result = match self.client.retry_policy().run(&self.file.url, || self.attempt(&part, tracker.as_deref())).await {
+            Ok(()) => tokio::fs::rename(&part, path).await.map_err(|err| Error::io(path, err)),
+            Err(err) => Err(err)
+        };
+        self.report_result(tracker.as_deref(), &result);
+        if result.is_ok() {
+            log::debug!("Downloaded {} to {}", self.file.url, path.display());
+        }
+        result
+    }
+
+    /// Downloads the file and everything it includes into `dir`, returning
+    /// the path of the file itself.
+    ///
+    /// The file is named after the last segment of its URL, and included files
+    /// are placed at their paths relative to it, so that a glTF or blend file
+    /// finds its textures and buffers. Files included by an included file are
+    /// relative to that file instead. Every file is verified, and nothing is
+    /// downloaded if any path is absolute or contains `..`.
+    pub async fn to_dir(self, dir: impl AsRef<Path>) -> Result<PathBuf> {
+        let dir = dir.as_ref();
+        let files = package_files(self.file)?;
+        let tracker = self.tracker(&files.iter().map(|(_, file)| *file).collect::<Vec<_>>());
+        for (path, file) in &files {
+            let download = Download {
+                client: self.client,
+                file,
+                observer: None,
+                tracker: tracker.clone(),
+                bandwidth: self.bandwidth.clone()
+            };
+            download.to_path(dir.join(path)).await?;
+        }
+        Ok(dir.join(&files[0].0))
+    }
+
+    /// Streams the file into `writer`.
+    ///
+    /// The file can only be verified once all of it has been written, so
+    /// `writer` may have received a corrupt file if this fails with
+    /// `Error::ChecksumMismatch` or `Error::SizeMismatch`. Since the writer
+    /// can't be rewound, failures aren't retried.
+    pub async fn to_writer<W: AsyncWrite + Unpin>(self, writer: &mut W) -> Result<()> {
+        let tracker = self.tracker(&[self.file]);
+        let result = match self.send(&HeaderMap::new()).await {
+            Ok((_permit, resp)) => self.write_body(resp, writer, Md5::new(), 0, tracker.as_deref(), Error::Write).await,
+            Err(err) => Err(err)
+        };
+        self.report_result(tracker.as_deref(), &result);
+        result
+    }
+
+    /// The tracker to report this download's progress to, if anything is
+    /// observing it. `files` are all the files it's made up of.
+    fn tracker(&self, files: &[&FileData]) -> Option<Arc<Tracker>> {
+        match (&self.tracker, &self.observer) {
+            (Some(tracker), _) => Some(tracker.clone()),
+            (None, Some(observer)) => Some(Arc::new(Tracker::new(observer.clone(), files.iter().copied()))),
+            (None, None) => None
+        }
+    }
+
+    fn report_result(&self, tracker: Option<&Tracker>, result: &Result<()>) {
+        match (tracker, result) {
+            (Some(tracker), Ok(())) => tracker.finished(&self.file.url),
+            (Some(tracker), Err(err)) => tracker.failed(&self.file.url, err),
+            (None, _) => {}
+        }
+    }
+
+    /// Makes a single attempt at downloading to `part`, resuming from where
+    /// an earlier attempt left off if possible.
+    async fn attempt(&self, part: &Path, tracker: Option<&Tracker>) -> Result<()> {
+        let offset = match tokio::fs::metadata(part).await {
+            Ok(metadata) if metadata.len() <= self.file.size => metadata.len(),
+            _ => 0
+        };
+        if offset > 0 {
+            match self.resume(part, offset, tracker).await {
+                Err(Error::ChecksumMismatch { .. } | Error::SizeMismatch { .. }) => {
+                    log::warn!("Resumed download of {} failed verification, restarting it", self.file.url);
+                },
+                Err(err) if err.status_code() == Some(StatusCode::RANGE_NOT_SATISFIABLE) => {
+                    log::debug!("{} can't be resumed from byte {}, restarting it", self.file.url, offset);
+                },
+                result => return result
+            }
+        }
+        self.restart(part, tracker).await
+    }
+
+    /// Continues a download into `part`, which already holds its first
+    /// `offset` bytes. Servers that ignore the `Range` header send the whole
+    /// file again, which replaces the partial one.
+    async fn resume(&self, part: &Path, offset: u64, tracker: Option<&Tracker>) -> Result<()> {
+        let existing = hash_file(part).await?;
+        if offset == self.file.size {
+            if let Some(tracker) = tracker {
+                tracker.started(&self.file.url, offset, offset);
+            }
+            return self.verify(offset, existing, tracker);
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, HeaderValue::from_str(&format!("bytes={}-", offset))?);
+        let (permit, resp) = self.send(&headers).await?;
+        if resp.status != StatusCode::PARTIAL_CONTENT {
+            return self.write_part(part, resp, None, tracker).await;
+        }
+        if range_start(&resp.headers) != Some(offset) {
+            log::debug!("{} sent the wrong range when resuming, restarting it", self.file.url);
+            drop((permit, resp));
+            return self.restart(part, tracker).await;
+        }
+        log::debug!("Resuming {} from byte {}", self.file.url, offset);
+        self.write_part(part, resp, Some((existing, offset)), tracker).await
+    }
+
+    /// Downloads the whole file into `part`, replacing anything there.
+    async fn restart(&self, part: &Path, tracker: Option<&Tracker>) -> Result<()> {
+        let (_permit, resp) = self.send(&HeaderMap::new()).await?;
+        self.write_part(part, resp, None, tracker).await
+    }
+
+    /// Writes a response body to `part`, appending to what's already there if
+    /// `resumed` gives its hash and length, and removes it if the download
+    /// turned out to be corrupt.
+    async fn write_part(&self, part: &Path, resp: HttpResponse, resumed: Option<(Md5, u64)>, tracker: Option<&Tracker>) -> Result<()> {
+        let opened = match resumed {
+            Some(_) => tokio::fs::OpenOptions::new().append(true).open(part).await,
+            None => tokio::fs::File::create(part).await
+        };
+        let mut file = opened.map_err(|err| Error::io(part, err))?;
+        let (hasher, written) = resumed.unwrap_or_default();
+        let result = match self.write_body(resp, &mut file, hasher, written, tracker, |err| Error::io(part, err)).await {
+            Ok(()) => file.sync_all().await.map_err(|err| Error::io(part, err)),
+            Err(err) => {
+                // Make sure everything received so far is on disk to resume from.
+                let _ = file.flush().await;
+                Err(err)
+            }
+        };
+        if let Err(Error::ChecksumMismatch { .. } | Error::SizeMismatch { .. }) = result {
+            let _ = tokio::fs::remove_file(part).await;
+        }
+        result
+    }
+
+    /// Streams a response body into `writer`, then verifies it. `hasher` and
+    /// `written` account for anything written before, when resuming.
+    async fn write_body<W: AsyncWrite + Unpin>(
+        &self,
+        mut resp: HttpResponse,
+        writer: &mut W,
+        mut hasher: Md5,
+        mut written: u64,
+        tracker: Option<&Tracker>,
+        io_error: impl Fn(io::Error) -> Error
+    ) -> Result<()> {
+        let url = &self.file.url;
+        let total = content_length(&resp.headers).map_or(self.file.size, |length| written + length);
+        if let Some(tracker) = tracker {
+            tracker.started(url, written, total);
+        }
+        while let Some(chunk) = resp.body.chunk().await {
+            let chunk = chunk?;
+            writer.write_all(&chunk).await.map_err(&io_error)?;
+            hasher.update(&chunk);
+            written += chunk.len() as u64;
+            if let Some(bandwidth) = &self.bandwidth {
+                tokio::time::sleep(bandwidth.reserve(chunk.len() as f64)).await;
+            }
+            if let Some(tracker) = tracker {
+                tracker.progress(url, written, total);
+            }
+        }
+        writer.flush().await.map_err(&io_error)?;
+        if written < self.file.size {
+            // Without a Content-Length, a dropped connection looks like the end
+            // of the body, so treat a short body as interrupted to resume it.
+            let message = format!("Connection closed after {} of {} bytes", written, self.file.size);
+            return Err(TransportError::new(TransportErrorKind::Interrupted, message).into());
+        }
+        self.verify(written, hasher, tracker)
+    }
+
+    fn verify(&self, size: u64, hasher: Md5, tracker: Option<&Tracker>) -> Result<()> {
+        let result = verify(self.file, size, hasher);
+        if let Some(tracker) = tracker {
+            tracker.verified(&self.file.url, result.is_ok());
+        }
+        result
+    }
+
+    async fn send(&self, headers: &HeaderMap) -> Result<(Permit, HttpResponse)> {
+        let url = Url::parse(&self.file.url).map_err(|_| Error::InvalidFileUrl(self.file.url.clone()))?;
+        self.client.send_get(&url, headers).await
+    }
+}
+
+/// Hashes a file that's already on disk.
+pub(crate) async fn hash_file(path: &Path) -> Result<Md5> {
+    let mut file = tokio::fs::File::open(path).await.map_err(|err| Error::io(path, err))?;
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await.map_err(|err| Error::io(path, err))?;
+        if read == 0 {
+            return Ok(hasher);
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers.get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+/// The first byte of a `206 Partial Content` response, from its
+/// `Content-Range` header, e.g. `bytes 1000-1999/2000`.
+fn range_start(headers: &HeaderMap) -> Option<u64> {
+    let range = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+/// Checks a download against the size and MD5 the API gave for it.
+fn verify(file: &FileData, size: u64, hasher: Md5) -> Result<()> {
+    if size != file.size {
+        return Err(Error::SizeMismatch { url: file.url.clone(), expected: file.size, actual: size });
+    }
+    let md5 = format!("{:x}", hasher.finalize());
+    if !md5.eq_ignore_ascii_case(&file.md5) {
+        return Err(Error::ChecksumMismatch { url: file.url.clone(), expected: file.md5.clone(), actual: md5 });
+    }
+    Ok(())
+}
+
+/// Every file in a package and its path relative to the package's directory,
+/// starting with `file` itself.
+pub(crate) fn package_files(file: &FileData) -> Result<Vec<(PathBuf, &FileData)>> {
+    let url = Url::parse(&file.url).map_err(|_| Error::InvalidFileUrl(file.url.clone()))?;
+    let name = url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(|name| percent_decode_str(name).decode_utf8_lossy().into_owned())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| Error::InvalidFileUrl(file.url.clone()))?;
+    let mut files = vec![(safe_relative_path(&name)?, file)];
+    add_includes(file, Path::new(""), &mut files)?;
+    Ok(files)
+}
+
+fn add_includes<'a>(file: &'a FileData, base: &Path, files: &mut Vec<(PathBuf, &'a FileData)>) -> Result<()> {
+    let mut includes = file.include.iter().collect::<Vec<_>>();
+    includes.sort_by_key(|(path, _)| *path);
+    for (path, included) in includes {
+        let path = base.join(safe_relative_path(path)?);
+        if files.iter().any(|(existing, _)| *existing == path) {
+            continue;
+        }
+        files.push((path.clone(), included));
+        add_includes(included, path.parent().unwrap_or(Path::new("")), files)?;
+    }
+    Ok(())
+}
+
+/// Checks that a path from the API stays inside the directory it's joined
+/// to. Backslashes are treated as separators, since they are on Windows, and
+/// so are drive prefixes like `C:`, which aren't special elsewhere. Empty
+/// components are refused too, which includes leading and trailing slashes.
+fn safe_relative_path(path: &str) -> Result<PathBuf> {
+    let normalized = path.replace('\\', "/");
+    let safe = normalized.split('/').all(|part| !part.is_empty() && part != ".." && !part.contains(':'));
+    if !safe {
+        return Err(Error::UnsafePath(path.to_string()));
+    }
+    Ok(PathBuf::from(normalized))
+}
+
+/// Whether an id can be used as a single file or directory name. Ids come
+/// from the API, so any that could escape the directory they're joined to
+/// are refused, including drive prefixes like `C:` on any platform.
+pub(crate) fn is_safe_id(id: &str) -> bool {
+    !id.is_empty() && !id.starts_with('.') && !id.contains(['/', '\\', ':'])
+}
+
+/// Where a download to `path` is written until it has been verified.
+pub(crate) fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+impl<T> fmt::Debug for Download<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Download")
+            .field("file", &self.file)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_relative_path_accepts_nested_includes() {
+        for path in ["brick.bin", "textures/brick_diff_1k.jpg", "textures\\brick_diff_1k.jpg", "./textures/a.jpg", "a/b/c/d.png"] {
+            let safe = safe_relative_path(path).unwrap_or_else(|err| panic!("{} was refused: {}", path, err));
+            assert!(safe.is_relative(), "{}", path);
+        }
+        assert_eq!(safe_relative_path("textures\\a.jpg").unwrap(), Path::new("textures/a.jpg"));
+    }
+
+    #[test]
+    fn safe_relative_path_refuses_escapes() {
+        let unsafe_paths = [
+            "",
+            "..",
+            "../brick.bin",
+            "textures/../../brick.bin",
+            "textures\\..\\..\\brick.bin",
+            "/etc/passwd",
+            "\\etc\\passwd",
+            "\\\\server\\share\\brick.bin",
+            "C:/Windows/brick.bin",
+            "C:\\Windows\\brick.bin",
+            "C:brick.bin",
+            "textures//brick.bin",
+            "textures/",
+            "/"
+        ];
+        for path in unsafe_paths {
+            assert!(matches!(safe_relative_path(path), Err(Error::UnsafePath(refused)) if refused == path), "{} was accepted", path);
+        }
+    }
+
+    #[test]
+    fn safe_ids_are_single_names() {
+        for id in ["brick_wall_001", "sky-2k", "Greg Zaal", "a.b"] {
+            assert!(is_safe_id(id), "{} was refused", id);
+        }
+    }
+
+    #[test]
+    fn unsafe_ids_are_refused() {
+        for id in ["", ".", "..", ".hidden", "a/b", "a\\b", "../a", "C:", "C:foo", "c:\\foo", "/etc", "a:b"] {
+            assert!(!is_safe_id(id), "{} was accepted", id);
+        }
+    }
+}
\ No newline at end of file
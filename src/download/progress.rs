@@ -0,0 +1,158 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use tokio::sync::{mpsc, watch};
+
+use crate::{data::files::FileData, Error};
+
+/// Something that happened to one file during a download.
+///
+/// Files are identified by their URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DownloadEvent {
+    /// A file started downloading. `done` is non-zero if it's resuming a
+    /// partial download. `total` comes from the response's `Content-Length`,
+    /// or the file's expected size if there isn't one.
+    Started {
+        url: String,
+        done: u64,
+        total: u64
+    },
+    /// More of a file was received.
+    Progress {
+        url: String,
+        done: u64,
+        total: u64
+    },
+    /// A file was checked against its size and MD5. A file that failed may
+    /// be started again, if it was resumed or can be retried.
+    Verified {
+        url: String,
+        passed: bool
+    },
+    /// A file was downloaded and verified.
+    Finished {
+        url: String
+    },
+    /// A file couldn't be downloaded, after any retries.
+    Failed {
+        url: String,
+        error: String
+    }
+}
+
+/// The overall progress of a download, across every file in it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// How many files the download is made up of.
+    pub files: usize,
+    pub files_finished: usize,
+    pub files_failed: usize,
+    /// Bytes received so far, including any resumed from partial downloads.
+    pub bytes_done: u64,
+    /// The expected size of every file, added up.
+    pub bytes_total: u64
+}
+
+impl Progress {
+    /// Whether every file has either finished or failed.
+    pub fn is_complete(&self) -> bool {
+        self.files_finished + self.files_failed >= self.files
+    }
+}
+
+/// Receives progress updates from a download. Give one to
+/// `Download::progress`.
+///
+/// This is implemented for closures taking the event and the overall
+/// progress, for `mpsc` senders of `DownloadEvent`s, and for `watch` senders
+/// of the overall `Progress`. Observers are called from the task running the
+/// download, so they should return quickly.
+pub trait ProgressObserver: Send + Sync {
+    fn on_event(&self, event: &DownloadEvent, overall: &Progress);
+}
+
+impl<F: Fn(&DownloadEvent, &Progress) + Send + Sync> ProgressObserver for F {
+    fn on_event(&self, event: &DownloadEvent, overall: &Progress) {
+        self(event, overall)
+    }
+}
+
+impl ProgressObserver for mpsc::UnboundedSender<DownloadEvent> {
+    fn on_event(&self, event: &DownloadEvent, _overall: &Progress) {
+        let _ = self.send(event.clone());
+    }
+}
+
+impl ProgressObserver for watch::Sender<Progress> {
+    fn on_event(&self, _event: &DownloadEvent, overall: &Progress) {
+        let _ = self.send(*overall);
+    }
+}
+
+/// Keeps the overall progress of a download and reports events to an
+/// observer.
+pub(crate) struct Tracker {
+    observer: Arc<dyn ProgressObserver>,
+    state: Mutex<TrackerState>
+}
+
+#[derive(Default)]
+struct TrackerState {
+    overall: Progress,
+    /// Bytes received so far for each file, by URL.
+    done: HashMap<String, u64>
+}
+
+impl Tracker {
+    pub fn new<'a>(observer: Arc<dyn ProgressObserver>, files: impl IntoIterator<Item = &'a FileData>) -> Self {
+        let mut overall = Progress::default();
+        for file in files {
+            overall.files += 1;
+            overall.bytes_total += file.size;
+        }
+        Self {
+            observer,
+            state: Mutex::new(TrackerState { overall, done: HashMap::new() })
+        }
+    }
+
+    pub fn started(&self, url: &str, done: u64, total: u64) {
+        self.set_done(url, done, DownloadEvent::Started { url: url.to_string(), done, total });
+    }
+
+    pub fn progress(&self, url: &str, done: u64, total: u64) {
+        self.set_done(url, done, DownloadEvent::Progress { url: url.to_string(), done, total });
+    }
+
+    pub fn verified(&self, url: &str, passed: bool) {
+        self.report(DownloadEvent::Verified { url: url.to_string(), passed }, |_| {});
+    }
+
+    pub fn finished(&self, url: &str) {
+        self.report(DownloadEvent::Finished { url: url.to_string() }, |state| state.overall.files_finished += 1);
+    }
+
+    pub fn failed(&self, url: &str, error: &Error) {
+        let event = DownloadEvent::Failed { url: url.to_string(), error: error.to_string() };
+        self.report(event, |state| state.overall.files_failed += 1);
+    }
+
+    fn set_done(&self, url: &str, done: u64, event: DownloadEvent) {
+        self.report(event, |state| {
+            let previous = state.done.insert(url.to_string(), done).unwrap_or(0);
+            state.overall.bytes_done = (state.overall.bytes_done + done).saturating_sub(previous);
+        });
+    }
+
+    /// Updates the state, then reports `event` with the new overall progress.
+    /// The observer is called without the lock held, so it may do anything.
+    fn report(&self, event: DownloadEvent, update: impl FnOnce(&mut TrackerState)) {
+        let overall = {
+            let mut state = self.state.lock().unwrap();
+            update(&mut state);
+            state.overall
+        };
+        self.observer.on_event(&event, &overall);
+    }
+}
\ No newline at end of file
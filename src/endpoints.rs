@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use serde::de::{DeserializeOwned, IgnoredAny};
+use url::Url;
+
+use crate::{data, json, request::{assets, categories}, Error, ParseContext, ParseMode, Parsed, Result};
+
+/// Everything about the API that doesn't involve sending requests: building
+/// endpoint URLs and parsing response bodies. Shared by the async and
+/// blocking clients.
+#[derive(Debug, Clone)]
+pub(crate) struct Endpoints {
+    pub api_url: Url,
+    pub cdn_url: Url,
+    pub parse_mode: ParseMode
+}
+
+impl Endpoints {
+    pub fn new(api_url: &str, cdn_url: &str, parse_mode: ParseMode) -> Result<Self> {
+        Ok(Self {
+            api_url: parse_base_url(api_url)?,
+            cdn_url: parse_base_url(cdn_url)?,
+            parse_mode
+        })
+    }
+
+    pub fn assets_url(&self, params: &assets::Params) -> Url {
+        let mut url = self.endpoint(&["assets"]);
+        params.query().apply_to(&mut url);
+        url
+    }
+
+    pub fn info_url(&self, id: &str) -> Url {
+        self.endpoint(&["info", id])
+    }
+
+    pub fn files_url(&self, id: &str) -> Url {
+        self.endpoint(&["files", id])
+    }
+
+    pub fn author_url(&self, id: &str) -> Url {
+        self.endpoint(&["author", id])
+    }
+
+    pub fn categories_url(&self, params: &categories::Params) -> Url {
+        let mut url = self.endpoint(&["categories", params.asset_type.api_name()]);
+        params.query().apply_to(&mut url);
+        url
+    }
+
+    pub fn thumbnail(&self, asset: &data::asset::AssetInfo, resolution: u32) -> String {
+        asset.thumbnail_on(self.cdn_url.as_str().trim_end_matches('/'), resolution)
+    }
+
+    /// Appends path segments to the API URL, percent-encoding each of them.
+    fn endpoint(&self, segments: &[&str]) -> Url {
+        let mut url = self.api_url.clone();
+        url.path_segments_mut()
+            .expect("API URL is checked to be a base when the client is built")
+            .pop_if_empty()
+            .extend(segments);
+        url
+    }
+
+    pub fn parse_assets(&self, url: &Url, body: &[u8]) -> Result<Parsed<HashMap<String, data::asset::AssetInfo>>> {
+        let resp = deserialize::<HashMap<String, json::asset::AssetInfo>>(url, body)?;
+        let mut ctx = ParseContext::new(self.parse_mode, "");
+        let mut assets = HashMap::with_capacity(resp.len());
+        for (id, json) in resp {
+            let info = data::asset::AssetInfo::from_json(json, id.clone(), &mut ctx)?;
+            assets.insert(id, info);
+        }
+        Ok(ctx.finish(assets))
+    }
+
+    pub fn parse_info(&self, url: &Url, body: &[u8], id: &str) -> Result<Parsed<data::asset::AssetInfo>> {
+        let resp = deserialize::<json::asset::AssetInfo>(url, body)?;
+        let mut ctx = ParseContext::new(self.parse_mode, id);
+        let info = data::asset::AssetInfo::from_json(resp, id.to_string(), &mut ctx)?;
+        Ok(ctx.finish(info))
+    }
+
+    /// Reads just the asset type from an `/info` response.
+    pub fn parse_info_asset_type(&self, url: &Url, body: &[u8]) -> Result<data::asset::AssetType> {
+        let resp = deserialize::<json::asset::AssetInfo>(url, body)?;
+        json::asset::asset_type(resp.asset_type).ok_or(Error::UnknownAssetType(resp.asset_type))
+    }
+
+    pub fn parse_files(&self, url: &Url, body: &[u8], id: &str, asset_type: &data::asset::AssetType) -> Result<Parsed<data::files::Files>> {
+        use data::asset::AssetType;
+        let files_json = match asset_type {
+            AssetType::HDRI => json::files::Files::HDRI(deserialize(url, body)?),
+            AssetType::Texture => json::files::Files::Texture(deserialize(url, body)?),
+            AssetType::Model => json::files::Files::Model(deserialize(url, body)?)
+        };
+        let mut ctx = ParseContext::new(self.parse_mode, id);
+        let files = data::files::Files::from_json(files_json, &mut ctx)?;
+        Ok(ctx.finish(files))
+    }
+
+    /// Parses a `/files` response, working out the asset type from its keys.
+    pub fn parse_files_by_shape(&self, url: &Url, body: &[u8], id: &str) -> Result<Parsed<data::files::Files>> {
+        let keys = deserialize::<HashMap<String, IgnoredAny>>(url, body)?;
+        self.parse_files(url, body, id, &json::files::detect_asset_type(keys.keys()))
+    }
+
+    pub fn parse_author(&self, url: &Url, body: &[u8]) -> Result<data::author::Author> {
+        Ok(deserialize::<json::author::Author>(url, body)?.into())
+    }
+
+    pub fn parse_categories(&self, url: &Url, body: &[u8]) -> Result<HashMap<String, u32>> {
+        deserialize(url, body)
+    }
+}
+
+/// Unwraps a lenient parse result, logging anything that was skipped.
+pub(crate) fn log_warnings<T>(parsed: Parsed<T>) -> T {
+    for warning in &parsed.warnings {
+        log::warn!("Skipped invalid data from the PolyHaven API: {}", warning);
+    }
+    parsed.value
+}
+
+pub(crate) fn deserialize<T: DeserializeOwned>(url: &Url, body: &[u8]) -> Result<T> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(body);
+    serde_path_to_error::deserialize(deserializer).map_err(|err| Error::Deserialize {
+        url: url.to_string(),
+        path: err.path().to_string(),
+        source: err.into_inner()
+    })
+}
+
+fn parse_base_url(url: &str) -> Result<Url> {
+    match Url::parse(url) {
+        Ok(parsed) if !parsed.cannot_be_a_base() => Ok(parsed),
+        _ => Err(Error::InvalidBaseUrl(url.to_string()))
+    }
+}
@@ -0,0 +1,170 @@
+use std::{io, path::{Path, PathBuf}, time::Duration};
+
+use reqwest::StatusCode;
+
+use crate::{transport::TransportError, ParseWarning};
+
+/// The longest response body, in bytes, kept in `Error::Status`.
+const BODY_SNIPPET_LEN: usize = 512;
+
+/// Everything that can go wrong while talking to the PolyHaven API.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The request couldn't be sent or the response couldn't be read, for
+    /// example because of a connection failure or timeout.
+    #[error("HTTP transport error: {0}")]
+    Transport(#[from] TransportError),
+
+    /// The server responded with a non-2xx status other than 404.
+    #[error("{url} responded with {status}: {body}")]
+    Status {
+        url: String,
+        status: StatusCode,
+        /// How long the server asked clients to wait before retrying, from
+        /// its `Retry-After` header.
+        retry_after: Option<Duration>,
+        /// The start of the response body, which usually explains the error.
+        body: String
+    },
+
+    /// The server responded with 404, usually because of an unknown asset or
+    /// author id.
+    #[error("{url} was not found")]
+    NotFound {
+        url: String
+    },
+
+    /// The response body didn't match the expected JSON schema.
+    #[error("Couldn't deserialize response from {url} at `{path}`: {source}")]
+    Deserialize {
+        url: String,
+        /// The JSON path of the value that failed, e.g. `hdri.4k.exr.size`.
+        path: String,
+        #[source]
+        source: serde_json::Error
+    },
+
+    /// An asset's numeric `type` isn't one this crate knows about.
+    #[error("Unknown asset type {0}")]
+    UnknownAssetType(i32),
+
+    /// An asset type name couldn't be parsed, e.g. `"sounds"`.
+    #[error("Couldn't parse asset type `{0}`")]
+    InvalidAssetType(String),
+
+    /// Part of a response couldn't be parsed in `ParseMode::Strict`.
+    #[error("Invalid data for {0}")]
+    InvalidData(ParseWarning),
+
+    /// A file resolution couldn't be parsed, e.g. `"4x"`.
+    #[error("Couldn't parse file resolution `{0}`")]
+    InvalidResolution(String),
+
+    /// A configured base URL couldn't be parsed or can't have paths
+    /// appended to it.
+    #[error("Invalid base URL `{0}`")]
+    InvalidBaseUrl(String),
+
+    /// A configured header, such as the User-Agent, isn't a valid header value.
+    #[error("Invalid header value: {0}")]
+    InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
+
+    /// A file couldn't be read or written.
+    #[error("I/O error at {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error
+    },
+
+    /// A file URL from the API couldn't be parsed.
+    #[error("Invalid file URL `{0}`")]
+    InvalidFileUrl(String),
+
+    /// A downloaded file wasn't the size the API said it would be.
+    #[error("{url} was {actual} bytes, expected {expected}")]
+    SizeMismatch {
+        url: String,
+        expected: u64,
+        actual: u64
+    },
+
+    /// A downloaded file's MD5 didn't match the one the API gave for it.
+    #[error("{url} has MD5 {actual}, expected {expected}")]
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String
+    },
+
+    /// A file included by another would be written outside the directory it's
+    /// being downloaded to, because its path is absolute or contains `..`.
+    #[error("Refusing to download to unsafe path `{0}`")]
+    UnsafePath(String),
+
+    /// A download couldn't be written to its destination.
+    #[error("Couldn't write download: {0}")]
+    Write(#[source] io::Error),
+
+    /// A job in a batch had the same destination as an earlier job, but a
+    /// different file.
+    #[error("{} is already the destination of a different file in the batch", path.display())]
+    DuplicateDestination {
+        path: PathBuf
+    },
+
+    /// A catalog snapshot couldn't be loaded or saved.
+    #[error("Invalid snapshot at {}: {message}", path.display())]
+    InvalidSnapshot {
+        path: PathBuf,
+        message: String
+    },
+
+    /// A library's manifest couldn't be loaded or saved.
+    #[error("Invalid library manifest at {}: {message}", path.display())]
+    InvalidManifest {
+        path: PathBuf,
+        message: String
+    }
+}
+
+impl Error {
+    pub(crate) fn status(url: String, status: StatusCode, retry_after: Option<Duration>, body: &str) -> Self {
+        let mut end = body.len().min(BODY_SNIPPET_LEN);
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        Self::Status { url, status, retry_after, body: body[..end].to_string() }
+    }
+
+    pub(crate) fn io(path: &Path, source: io::Error) -> Self {
+        Self::Io { path: path.to_path_buf(), source }
+    }
+
+    pub(crate) fn invalid_snapshot(path: &Path, message: impl Into<String>) -> Self {
+        Self::InvalidSnapshot { path: path.to_path_buf(), message: message.into() }
+    }
+
+    pub(crate) fn invalid_manifest(path: &Path, message: impl Into<String>) -> Self {
+        Self::InvalidManifest { path: path.to_path_buf(), message: message.into() }
+    }
+
+    /// The HTTP status the server responded with, if this error came from a
+    /// response.
+    pub fn status_code(&self) -> Option<StatusCode> {
+        match self {
+            Self::Status { status, .. } => Some(*status),
+            Self::NotFound { .. } => Some(StatusCode::NOT_FOUND),
+            _ => None
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Transport(err.into())
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
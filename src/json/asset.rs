@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::DateTime;
 use serde::Deserialize;
 
-use crate::data::asset;
+use crate::{data::asset, ParseContext, Result};
 
 #[derive(Deserialize)]
 pub struct AssetInfo {
@@ -25,30 +25,58 @@ pub struct AssetInfo {
     pub dimensions: Option<(f32, f32)>
 }
 
+/// The asset type for a numeric `type` value, if it's one this crate knows.
+pub fn asset_type(value: i32) -> Option<asset::AssetType> {
+    match value {
+        0 => Some(asset::AssetType::HDRI),
+        1 => Some(asset::AssetType::Texture),
+        2 => Some(asset::AssetType::Model),
+        _ => None
+    }
+}
+
 impl asset::AssetInfo {
-    pub fn from_json(json: AssetInfo, id: String) -> Self {
-        Self {
-            id: id,
+    pub fn from_json(json: AssetInfo, id: String, ctx: &mut ParseContext) -> Result<Self> {
+        ctx.set_asset(id.as_str());
+        let asset = match json.asset_type {
+            0 => asset::Asset::HDRI(asset::HDRIAsset {
+                whitebalance: json.whitebalance,
+                backplates: json.backplates.unwrap_or(false),
+                evs_cap: json.evs_cap.unwrap_or(0),
+                coords: json.coords
+            }),
+            1 => asset::Asset::Texture(asset::TextureAsset {
+                dimensions: match json.dimensions {
+                    Some(dimensions) => dimensions,
+                    None => {
+                        ctx.warn("dimensions", "Missing for a texture, defaulting to (0, 0)")?;
+                        (0.0, 0.0)
+                    }
+                }
+            }),
+            2 => asset::Asset::Model(asset::ModelAsset),
+            other => {
+                ctx.warn("type", format!("Unknown asset type {}", other))?;
+                asset::Asset::Unparsed
+            }
+        };
+        let date_published = match DateTime::from_timestamp(json.date_published, 0) {
+            Some(date) => date,
+            None => {
+                ctx.warn("date_published", format!("Timestamp {} is out of range, defaulting to the epoch", json.date_published))?;
+                DateTime::default()
+            }
+        };
+        Ok(Self {
+            id,
             name: json.name,
-            date_published: DateTime::from_utc(NaiveDateTime::from_timestamp(json.date_published, 0), Utc),
+            date_published,
             download_count: json.download_count,
             authors: json.authors,
             donated: json.donated.unwrap_or(false),
             categories: json.categories,
             tags: json.tags,
-            asset: match json.asset_type {
-                0 => asset::Asset::HDRI(asset::HDRIAsset {
-                    whitebalance: json.whitebalance,
-                    backplates: json.backplates.unwrap_or(false),
-                    evs_cap: json.evs_cap.unwrap_or(0),
-                    coords: json.coords
-                }),
-                1 => asset::Asset::Texture(asset::TextureAsset {
-                    dimensions: json.dimensions.unwrap_or((0.0, 0.0)),
-                }),
-                2 => asset::Asset::Model(asset::ModelAsset),
-                _ => asset::Asset::Unparsed
-            },
-        }
+            asset
+        })
     }
 }
\ No newline at end of file
@@ -1,23 +1,60 @@
-use anyhow::Result;
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::{hash_map::Entry, HashMap}, str::FromStr};
 
 use serde::Deserialize;
 
-use crate::data::files;
+use crate::{data::{asset::AssetType, files}, ParseContext, Result};
 
-fn parse_resolution(res_str: &str) -> Result<u64> {
-    if res_str.ends_with("k") || res_str.ends_with("K") {
-        match res_str[..res_str.len() - 1].parse::<u64>() {
-            Ok(num) => Ok(num * 1024),
-            Err(_) => anyhow::bail!("Couldn't parse file resolution")
+/// Parses the resolution keys of `json`, converting each value with
+/// `convert`. Resolutions that can't be parsed are reported under `field`.
+///
+/// Keys that are spelled differently but mean the same resolution, like `1k`
+/// and `1024`, are reported too. Keys are read in sorted order and the first
+/// of them is kept, so the result doesn't depend on the order of the JSON.
+fn parse_resolutions<J, D>(
+    json: HashMap<FileResolution, J>,
+    field: &str,
+    ctx: &mut ParseContext,
+    mut convert: impl FnMut(J) -> D
+) -> Result<HashMap<files::Resolution, D>> {
+    let mut json = json.into_iter().collect::<Vec<_>>();
+    json.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut parsed = HashMap::new();
+    let mut keys = HashMap::new();
+    for (res_str, value) in json {
+        let res = match res_str.parse::<files::Resolution>() {
+            Ok(res) => res,
+            Err(err) => {
+                ctx.warn(format!("{}.{}", field, res_str), err.to_string())?;
+                continue;
+            }
+        };
+        match keys.entry(res) {
+            Entry::Occupied(kept) => {
+                let message = format!("{} is the same resolution as {}, which is used instead", res_str, kept.get());
+                ctx.warn(format!("{}.{}", field, res_str), message)?;
+            },
+            Entry::Vacant(key) => {
+                key.insert(res_str);
+                parsed.insert(res, convert(value));
+            }
         }
-    } else {
-        match res_str.parse() {
-            Ok(num) => Ok(num),
-            Err(_) => anyhow::bail!("Couldn't parse file resolution")
+    }
+    Ok(parsed)
+}
+
+/// Works out which type of asset a `/files` response describes from its
+/// top-level keys: only HDRIs have `hdri` files and only models have `fbx`
+/// files, so anything else is a texture.
+pub fn detect_asset_type<K: AsRef<str>>(keys: impl IntoIterator<Item = K>) -> AssetType {
+    let mut asset_type = AssetType::Texture;
+    for key in keys {
+        match key.as_ref() {
+            "hdri" => return AssetType::HDRI,
+            "fbx" => asset_type = AssetType::Model,
+            _ => {}
         }
     }
-    
+    asset_type
 }
 
 pub enum Files {
@@ -26,13 +63,13 @@ pub enum Files {
     Model(ModelFiles)
 }
 
-impl From<Files> for files::Files {
-    fn from(json: Files) -> Self {
-        match json {
-            Files::HDRI(data) => files::Files::HDRI(data.into()),
-            Files::Texture(data) => files::Files::Texture(data.into()),
-            Files::Model(data) => files::Files::Model(data.into())
-        }
+impl files::Files {
+    pub fn from_json(json: Files, ctx: &mut ParseContext) -> Result<Self> {
+        Ok(match json {
+            Files::HDRI(data) => files::Files::HDRI(files::HDRIFiles::from_json(data, ctx)?),
+            Files::Texture(data) => files::Files::Texture(files::TextureFiles::from_json(data, ctx)?),
+            Files::Model(data) => files::Files::Model(files::ModelFiles::from_json(data, ctx)?)
+        })
     }
 }
 
@@ -100,25 +137,17 @@ pub struct HDRIFiles {
     pub tonemapped: Option<FileData>
 } 
 
-impl From<HDRIFiles> for files::HDRIFiles {
-    fn from(json: HDRIFiles) -> Self {
-        Self {
-            hdri: json.hdri.into_iter()
-                .filter_map(|(res_str, formats_strs)| {
-                    match parse_resolution(&res_str) {
-                        Ok(res) => {
-                            let formats = formats_strs.into_iter()
-                                .map(|(format_str, data)| (
-                                    files::HDRIFormat::from_str(&format_str).unwrap(), 
-                                    files::FileData::from(data)
-                                ))
-                                .collect();
-                            Some((res, formats))
-                        },
-                        Err(_) => None
-                    }
-                })
-                .collect(),
+impl files::HDRIFiles {
+    pub fn from_json(json: HDRIFiles, ctx: &mut ParseContext) -> Result<Self> {
+        Ok(Self {
+            hdri: parse_resolutions(json.hdri, "hdri", ctx, |formats_strs| {
+                formats_strs.into_iter()
+                    .map(|(format_str, data)| (
+                        files::HDRIFormat::from_str(&format_str).unwrap(),
+                        files::FileData::from(data)
+                    ))
+                    .collect()
+            })?,
             backplates: json.backplates.into_iter()
                 .map(|(name, formats_strs)| {
                     let formats = formats_strs.into_iter()
@@ -130,9 +159,9 @@ impl From<HDRIFiles> for files::HDRIFiles {
                     (name, formats)
                 })
                 .collect(),
-            colorchart: json.colorchart.and_then(|data| Some(files::FileData::from(data))),
-            tonemapped: json.tonemapped.and_then(|data| Some(files::FileData::from(data)))
-        }
+            colorchart: json.colorchart.map(files::FileData::from),
+            tonemapped: json.tonemapped.map(files::FileData::from)
+        })
     }
 }
 
@@ -140,156 +169,108 @@ pub type HDRIFormat = String;
 
 pub type HDRIBackplateFormat = String;
 
+/// Top-level keys of texture and model `/files` responses that have their own
+/// fields. Every other key is a texture map.
+const STRUCTURAL_KEYS: &[&str] = &["blend", "gltf", "fbx"];
+
+pub type MapFiles = HashMap<FileResolution, HashMap<TextureFormat, FileData>>;
+
+fn maps_from_json(maps_json: HashMap<String, serde_json::Value>, ctx: &mut ParseContext) -> Result<files::TextureMaps> {
+    let mut maps = HashMap::new();
+    for (map_name, map_value) in maps_json {
+        if STRUCTURAL_KEYS.contains(&map_name.as_str()) {
+            continue;
+        }
+        let map_json = match serde_json::from_value::<MapFiles>(map_value) {
+            Ok(map_json) => map_json,
+            Err(err) => {
+                ctx.warn(map_name, format!("Couldn't parse texture map: {}", err))?;
+                continue;
+            }
+        };
+        let map = parse_resolutions(map_json, &map_name, ctx, |formats_strs| {
+            formats_strs.into_iter()
+                .map(|(format_str, file_json)| (
+                    files::TextureFormat::from_str(&format_str).unwrap(),
+                    files::FileData::from(file_json)
+                ))
+                .collect()
+        })?;
+        maps.insert(files::TextureMap::from_str(&map_name).unwrap(), map);
+    }
+    Ok(maps)
+}
+
 #[derive(Deserialize)]
-#[allow(non_snake_case)]
 pub struct TextureFiles {
     pub blend: HashMap<FileResolution, BlendFileData>,
     pub gltf: HashMap<FileResolution, GltfFileData>,
 
-    pub AO: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub arm: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub Bump: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub Diffuse: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub Displacement: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub Metal: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub nor_gl: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub Rough: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub spec: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>
+    /// Every other key, each of which is a texture map.
+    #[serde(flatten)]
+    pub maps: HashMap<String, serde_json::Value>
 }
 
-impl From<TextureFiles> for files::TextureFiles {
-    fn from(json: TextureFiles) -> Self {
-        let mut maps_json = HashMap::new();
-        if let Some(map_json) = json.AO { maps_json.insert("AO", map_json); }
-        if let Some(map_json) = json.arm { maps_json.insert("arm", map_json); }
-        if let Some(map_json) = json.Bump { maps_json.insert("Bump", map_json); }
-        if let Some(map_json) = json.Diffuse { maps_json.insert("Diffuse", map_json); }
-        if let Some(map_json) = json.Displacement { maps_json.insert("Displacement", map_json); }
-        if let Some(map_json) = json.Metal { maps_json.insert("Metal", map_json); }
-        if let Some(map_json) = json.nor_gl { maps_json.insert("nor_gl", map_json); }
-        if let Some(map_json) = json.Rough { maps_json.insert("Rough", map_json); }
-        if let Some(map_json) = json.spec { maps_json.insert("spec", map_json); }
-        Self {
-            blend: json.blend.into_iter()
-                .filter_map(|(res_str, file_json)| {
-                    match parse_resolution(&res_str) {
-                        Ok(res) => Some((res, files::FileData::from(FileData::from(file_json)))),
-                        Err(_) => None
-                    }
-                })
-                .collect(),
-            gltf: json.gltf.into_iter()
-                .filter_map(|(res_str, file_json)| {
-                    match parse_resolution(&res_str) {
-                        Ok(res) => Some((res, files::FileData::from(FileData::from(file_json)))),
-                        Err(_) => None
-                    }
-                })
-                .collect(),
-            maps: maps_json.into_iter()
-                .map(|(map_name, map_json)| {
-                    let map = map_json.into_iter()
-                        .filter_map(|(res_str, formats_strs)| {
-                            match parse_resolution(&res_str) {
-                                Ok(res) => {
-                                    let formats = formats_strs.into_iter()
-                                        .map(|(format_str, file_json)| (
-                                            files::TextureFormat::from_str(&format_str).unwrap(),
-                                            files::FileData::from(file_json)
-                                        ))
-                                        .collect::<HashMap<_, _>>();
-                                    Some((res, formats))
-                                },
-                                Err(_) => None
-                            }
-                        })
-                        .collect::<HashMap<_, _>>();
-                    (files::TextureMap::from_str(map_name).unwrap(), map)
-                })
-                .collect(),
-        }
+impl files::TextureFiles {
+    pub fn from_json(json: TextureFiles, ctx: &mut ParseContext) -> Result<Self> {
+        Ok(Self {
+            blend: parse_resolutions(json.blend, "blend", ctx, |file_json| FileData::from(file_json).into())?,
+            gltf: parse_resolutions(json.gltf, "gltf", ctx, |file_json| FileData::from(file_json).into())?,
+            maps: maps_from_json(json.maps, ctx)?
+        })
     }
 }
 
 pub type TextureFormat = String;
 
 #[derive(Deserialize)]
-#[allow(non_snake_case)]
 pub struct ModelFiles {
     pub blend: HashMap<FileResolution, BlendFileData>,
     pub gltf: HashMap<FileResolution, GltfFileData>,
     pub fbx: HashMap<FileResolution, FbxFileData>,
 
-    pub AO: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub arm: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub Bump: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub Diffuse: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub Displacement: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub Metal: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub nor_gl: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub Rough: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>,
-    pub spec: Option<HashMap<FileResolution, HashMap<TextureFormat, FileData>>>
+    /// Every other key, each of which is a texture map.
+    #[serde(flatten)]
+    pub maps: HashMap<String, serde_json::Value>
 }
 
-impl From<ModelFiles> for files::ModelFiles {
-    fn from(json: ModelFiles) -> Self {
-        let mut maps_json = HashMap::new();
-        if let Some(map_json) = json.AO { maps_json.insert("AO", map_json); }
-        if let Some(map_json) = json.arm { maps_json.insert("arm", map_json); }
-        if let Some(map_json) = json.Bump { maps_json.insert("Bump", map_json); }
-        if let Some(map_json) = json.Diffuse { maps_json.insert("Diffuse", map_json); }
-        if let Some(map_json) = json.Displacement { maps_json.insert("Displacement", map_json); }
-        if let Some(map_json) = json.Metal { maps_json.insert("Metal", map_json); }
-        if let Some(map_json) = json.nor_gl { maps_json.insert("nor_gl", map_json); }
-        if let Some(map_json) = json.Rough { maps_json.insert("Rough", map_json); }
-        if let Some(map_json) = json.spec { maps_json.insert("spec", map_json); }
+impl files::ModelFiles {
+    pub fn from_json(json: ModelFiles, ctx: &mut ParseContext) -> Result<Self> {
+        Ok(Self {
+            blend: parse_resolutions(json.blend, "blend", ctx, |file_json| FileData::from(file_json).into())?,
+            gltf: parse_resolutions(json.gltf, "gltf", ctx, |file_json| FileData::from(file_json).into())?,
+            fbx: parse_resolutions(json.fbx, "fbx", ctx, |file_json| FileData::from(file_json).into())?,
+            maps: maps_from_json(json.maps, ctx)?
+        })
+    }
+}
 
-        Self {
-            blend: json.blend.into_iter()
-                .filter_map(|(res_str, file_json)| {
-                    match parse_resolution(&res_str) {
-                        Ok(res) => Some((res, files::FileData::from(FileData::from(file_json)))),
-                        Err(_) => None
-                    }
-                })
-                .collect(),
-            gltf: json.gltf.into_iter()
-                .filter_map(|(res_str, file_json)| {
-                    match parse_resolution(&res_str) {
-                        Ok(res) => Some((res, files::FileData::from(FileData::from(file_json)))),
-                        Err(_) => None
-                    }
-                })
-                .collect(),
-            fbx: json.fbx.into_iter()
-                .filter_map(|(res_str, file_json)| {
-                    match parse_resolution(&res_str) {
-                        Ok(res) => Some((res, files::FileData::from(FileData::from(file_json)))),
-                        Err(_) => None
-                    }
-                })
-                .collect(),
-            maps: maps_json.into_iter()
-                .map(|(map_name, map_json)| {
-                    let map = map_json.into_iter()
-                        .filter_map(|(res_str, formats_strs)| {
-                            match parse_resolution(&res_str) {
-                                Ok(res) => {
-                                    let formats = formats_strs.into_iter()
-                                        .map(|(format_str, file_json)| (
-                                            files::TextureFormat::from_str(&format_str).unwrap(),
-                                            files::FileData::from(file_json)
-                                        ))
-                                        .collect::<HashMap<_, _>>();
-                                    Some((res, formats))
-                                },
-                                Err(_) => None
-                            }
-                        })
-                        .collect::<HashMap<_, _>>();
-                    (files::TextureMap::from_str(map_name).unwrap(), map)
-                })
-                .collect(),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, ParseMode};
+
+    fn resolutions(entries: &[(&str, &'static str)]) -> HashMap<FileResolution, &'static str> {
+        entries.iter().map(|(key, value)| (key.to_string(), *value)).collect()
+    }
+
+    #[test]
+    fn duplicate_resolution_keeps_first_key_and_warns() {
+        let json = resolutions(&[("1k", "from 1k"), ("1024", "from 1024"), ("2k", "from 2k")]);
+        let mut ctx = ParseContext::new(ParseMode::Lenient, "brick");
+        let parsed = parse_resolutions(json, "hdri", &mut ctx, |value| value).unwrap();
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[&"1k".parse().unwrap()], "from 1024");
+        assert_eq!(ctx.warnings().len(), 1);
+        assert_eq!(ctx.warnings()[0].field, "hdri.1k");
+    }
+
+    #[test]
+    fn duplicate_resolution_fails_in_strict_mode() {
+        let mut ctx = ParseContext::new(ParseMode::Strict, "brick");
+        let err = parse_resolutions(resolutions(&[("1k", "from 1k"), ("1024", "from 1024")]), "hdri", &mut ctx, |value| value).unwrap_err();
+
+        assert!(matches!(err, Error::InvalidData(warning) if warning.field == "hdri.1k"));
     }
 }
\ No newline at end of file
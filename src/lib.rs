@@ -13,6 +13,27 @@
 //! projects I’m working on. I don’t intend for this to be used widely at the
 //! moment.
 
+mod client;
+mod endpoints;
+mod error;
+mod parse;
+mod retry;
+mod throttle;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cache;
 pub mod data;
+pub mod download;
 pub mod json;
-pub mod request;
\ No newline at end of file
+pub mod library;
+pub mod request;
+pub mod snapshot;
+pub mod sync;
+pub mod transport;
+
+pub use client::{Client, ClientBuilder, DEFAULT_API_URL, DEFAULT_CDN_URL, DEFAULT_USER_AGENT};
+pub use error::{Error, Result};
+pub use parse::{ParseContext, ParseMode, ParseWarning, Parsed};
+pub use retry::RetryPolicy;
+pub use throttle::RateLimit;
\ No newline at end of file
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::data::{asset::AssetType, files::{Resolution, TextureMap}};
+
+/// The newest manifest format this version of the crate can read.
+pub(crate) const FORMAT_VERSION: u32 = 1;
+
+/// Everything installed in a library, saved as `manifest.json` in its root.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub(crate) version: u32,
+    /// Every installed asset, by id.
+    pub assets: BTreeMap<String, LibraryAsset>
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            assets: BTreeMap::new()
+        }
+    }
+}
+
+/// An asset with at least one file in a library.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryAsset {
+    #[serde(with = "string")]
+    pub asset_type: AssetType,
+    pub name: String,
+    /// The `date_published` of the `AssetInfo` the asset was last installed
+    /// from, which identifies the version of the asset that's installed.
+    #[serde(with = "rfc3339")]
+    pub date_published: DateTime<Utc>,
+    /// Every installed file, by its path relative to the library root, using
+    /// `/` separators.
+    pub files: BTreeMap<String, LibraryFile>
+}
+
+/// A file in a library, and the API's description of it when it was
+/// installed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryFile {
+    #[serde(with = "string")]
+    pub resolution: Resolution,
+    /// The format's name, like `exr`, or `blend`, `gltf` or `fbx` for
+    /// packages.
+    /// Files included by a package have the package's format.
+    pub format: String,
+    /// The texture map this is a file of. Not set for HDRIs, packages, or the
+    /// files packages include.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "optional_string")]
+    pub map: Option<TextureMap>,
+    pub url: String,
+    pub md5: String,
+    pub size: u64
+}
+
+/// Writes a value with `Display` and reads it with `FromStr`, since the data
+/// types only implement serde with the `serde` feature.
+mod string {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T: FromStr, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error>
+    where
+        T::Err: Display
+    {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+}
+
+mod optional_string {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none()
+        }
+    }
+
+    pub fn deserialize<'de, T: FromStr, D: Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T::Err: Display
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| value.parse().map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+mod rfc3339 {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&value)
+            .map(|date| date.with_timezone(&Utc))
+            .map_err(D::Error::custom)
+    }
+}
\ No newline at end of file
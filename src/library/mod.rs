@@ -0,0 +1,469 @@
+//! A local mirror of PolyHaven assets.
+//!
+//! A library is a directory laid out as `{type}/{id}/{resolution}/...`, like
+//! `textures/brick_wall_001/4k/brick_wall_001_diff_4k.exr`, with a
+//! `manifest.json` in its root recording every installed file and its MD5.
+//! Files are only written by `Library::install`, which downloads them with the
+//! usual size and MD5 checks. A file that no longer matches its manifest
+//! entry, because it was edited or corrupted, is never overwritten or removed;
+//! it's reported as a conflict instead. `Library::verify` finds such files,
+//! and `Library::repair` replaces them when asked to.
+
+use std::{collections::HashMap, fmt, path::{Path, PathBuf}, sync::Arc};
+
+use md5::Digest;
+
+use crate::{data::{asset::{AssetInfo, AssetType}, files::{FileData, Files, Resolution, TextureMap, TextureMaps}}, download::{self, DownloadEvent, Progress, ProgressObserver}, transport::Transport, Client, Error, Result};
+
+mod manifest;
+mod verify;
+
+pub use self::manifest::{LibraryAsset, LibraryFile, Manifest};
+pub use self::verify::{Corrupted, Verify, VerifyProgress, VerifyReport};
+
+/// The name of the manifest in a library's root.
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// A directory of installed assets. See the module docs for its layout.
+///
+/// Opening a library reads its manifest into memory, and every change writes
+/// it back, so only one `Library` should have a directory open at a time.
+#[derive(Debug, Clone)]
+pub struct Library {
+    root: PathBuf,
+    manifest: Manifest
+}
+
+/// Which of an asset's files to install or remove. An empty list matches
+/// everything, so the default selection matches every file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    pub resolutions: Vec<Resolution>,
+    /// Format names, like `exr` or `jpg`. The `blend`, `gltf` and `fbx`
+    /// packages of textures and models are selected by those names.
+    pub formats: Vec<String>,
+    /// Texture maps, for textures and models. Packages aren't affected by this.
+    pub maps: Vec<TextureMap>
+}
+
+impl Selection {
+    pub fn matches(&self, resolution: Resolution, format: &str, map: Option<&TextureMap>) -> bool {
+        (self.resolutions.is_empty() || self.resolutions.contains(&resolution))
+            && (self.formats.is_empty() || self.formats.iter().any(|wanted| wanted.eq_ignore_ascii_case(format)))
+            && (self.maps.is_empty() || map.is_none_or(|map| self.maps.contains(map)))
+    }
+}
+
+/// What `Install::run` did with each file, by its path relative to the
+/// library root.
+#[derive(Debug, Default)]
+pub struct InstallReport {
+    pub downloaded: Vec<String>,
+    /// Files that were already installed. Files the manifest already has
+    /// aren't hashed again; `verify` checks those.
+    pub unchanged: Vec<String>,
+    /// Files that exist but don't match the manifest, so weren't replaced.
+    pub conflicts: Vec<String>,
+    pub failed: Vec<(String, Arc<Error>)>
+}
+
+impl InstallReport {
+    /// Whether every selected file is now installed.
+    pub fn is_success(&self) -> bool {
+        self.conflicts.is_empty() && self.failed.is_empty()
+    }
+}
+
+/// What `Library::remove` did with each file, by its path relative to the
+/// library root.
+#[derive(Debug, Default)]
+pub struct RemoveReport {
+    /// Files that were deleted, or were already missing.
+    pub removed: Vec<String>,
+    /// Files that don't match the manifest, so were left in place and kept in
+    /// the manifest.
+    pub conflicts: Vec<String>,
+    /// Files that couldn't be checked or deleted. They're kept in the
+    /// manifest.
+    pub failed: Vec<(String, Arc<Error>)>
+}
+
+impl RemoveReport {
+    /// Whether every selected file was removed.
+    pub fn is_success(&self) -> bool {
+        self.conflicts.is_empty() && self.failed.is_empty()
+    }
+}
+
+impl Library {
+    /// Opens the library in `root`, which is created empty if it doesn't have
+    /// a manifest yet.
+    pub async fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        let path = root.join(MANIFEST_NAME);
+        let manifest = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice::<Manifest>(&bytes).map_err(|err| Error::invalid_manifest(&path, err.to_string()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Manifest::default(),
+            Err(err) => return Err(Error::io(&path, err))
+        };
+        if manifest.version > manifest::FORMAT_VERSION {
+            return Err(Error::invalid_manifest(&path, format!(
+                "Format version {} is newer than this crate supports ({})",
+                manifest.version, manifest::FORMAT_VERSION
+            )));
+        }
+        Ok(Self { root, manifest })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    pub fn asset(&self, id: &str) -> Option<&LibraryAsset> {
+        self.manifest.assets.get(id)
+    }
+
+    /// The installed files of `id` at `resolution` in `format`, by their
+    /// path relative to the library root.
+    pub fn files<'a>(&'a self, id: &str, resolution: Resolution, format: &'a str) -> impl Iterator<Item = (&'a str, &'a LibraryFile)> {
+        self.asset(id)
+            .into_iter()
+            .flat_map(|asset| &asset.files)
+            .filter(move |(_, file)| file.resolution == resolution && file.format.eq_ignore_ascii_case(format))
+            .map(|(path, file)| (path.as_str(), file))
+    }
+
+    /// Whether `id` has any files installed at `resolution` in `format`,
+    /// such as an HDRI at 4k in EXR.
+    ///
+    /// This only checks the manifest; use `verify` to check the files
+    /// themselves.
+    pub fn is_available(&self, id: &str, resolution: Resolution, format: &str) -> bool {
+        self.files(id, resolution, format).next().is_some()
+    }
+
+    /// The resolutions `id` has files installed at, smallest first.
+    pub fn resolutions(&self, id: &str) -> Vec<Resolution> {
+        let mut resolutions = self.asset(id)
+            .into_iter()
+            .flat_map(|asset| asset.files.values().map(|file| file.resolution))
+            .collect::<Vec<_>>();
+        resolutions.sort();
+        resolutions.dedup();
+        resolutions
+    }
+
+    /// The formats `id` has files installed in at `resolution`.
+    pub fn formats(&self, id: &str, resolution: Resolution) -> Vec<&str> {
+        let mut formats = self.asset(id)
+            .into_iter()
+            .flat_map(|asset| asset.files.values())
+            .filter(|file| file.resolution == resolution)
+            .map(|file| file.format.as_str())
+            .collect::<Vec<_>>();
+        formats.sort();
+        formats.dedup();
+        formats
+    }
+
+    /// Starts installing the files of `info` that `selection` matches, from
+    /// its `files` listing. Nothing happens until `Install::run`.
+    ///
+    /// HDRI backplates, color charts and tonemapped previews aren't installed,
+    /// since they don't have a resolution to be filed under.
+    pub fn install<'a, T>(&'a mut self, client: &'a Client<T>, info: &'a AssetInfo, files: &'a Files, selection: &Selection) -> Install<'a, T> {
+        Install {
+            library: self,
+            client,
+            info,
+            files,
+            selection: selection.clone(),
+            concurrency: 4,
+            bytes_per_second: None,
+            observer: None
+        }
+    }
+
+    /// Deletes the installed files of `id` that `selection` matches, and
+    /// forgets the asset once none are left.
+    pub async fn remove(&mut self, id: &str, selection: &Selection) -> Result<RemoveReport> {
+        self.remove_where(id, |_, file| selection.matches(file.resolution, &file.format, file.map.as_ref())).await
+    }
+
+    /// Deletes the installed files of `id` that `predicate` matches, given
+    /// their path relative to the root and their manifest entry. A file that
+    /// can't be removed doesn't stop the others, and the manifest is saved
+    /// either way.
+    pub(crate) async fn remove_where(&mut self, id: &str, predicate: impl Fn(&str, &LibraryFile) -> bool) -> Result<RemoveReport> {
+        let mut report = RemoveReport::default();
+        let Some(asset) = self.manifest.assets.get_mut(id) else {
+            return Ok(report);
+        };
+        let selected = asset.files.iter()
+            .filter(|(path, file)| predicate(path, file))
+            .map(|(path, file)| (path.clone(), file.md5.clone()))
+            .collect::<Vec<_>>();
+        for (relative, md5) in selected {
+            let path = self.root.join(&relative);
+            let removed = match file_md5(&path).await {
+                Ok(Some(actual)) if !actual.eq_ignore_ascii_case(&md5) => {
+                    report.conflicts.push(relative);
+                    continue;
+                },
+                Ok(Some(_)) => tokio::fs::remove_file(&path).await.map_err(|err| Error::io(&path, err)),
+                Ok(None) => Ok(()),
+                Err(err) => Err(err)
+            };
+            if let Err(err) = removed {
+                report.failed.push((relative, Arc::new(err)));
+                continue;
+            }
+            remove_empty_dirs(&self.root, &path).await;
+            asset.files.remove(&relative);
+            report.removed.push(relative);
+        }
+        if asset.files.is_empty() {
+            self.manifest.assets.remove(id);
+        }
+        self.save().await?;
+        Ok(report)
+    }
+
+    /// Writes the manifest, replacing the old one only once it's complete.
+    async fn save(&self) -> Result<()> {
+        let path = self.root.join(MANIFEST_NAME);
+        let bytes = serde_json::to_vec_pretty(&self.manifest).map_err(|err| Error::invalid_manifest(&path, err.to_string()))?;
+        tokio::fs::create_dir_all(&self.root).await.map_err(|err| Error::io(&self.root, err))?;
+        let part = download::part_path(&path);
+        tokio::fs::write(&part, bytes).await.map_err(|err| Error::io(&part, err))?;
+        tokio::fs::rename(&part, &path).await.map_err(|err| Error::io(&path, err))
+    }
+}
+
+/// An installation of an asset's files, started with `Library::install`.
+pub struct Install<'a, T> {
+    library: &'a mut Library,
+    client: &'a Client<T>,
+    info: &'a AssetInfo,
+    files: &'a Files,
+    selection: Selection,
+    concurrency: usize,
+    bytes_per_second: Option<u64>,
+    observer: Option<Arc<dyn ProgressObserver>>
+}
+
+impl<T: Transport> Install<'_, T> {
+    /// Sets how many files are downloaded at once. Defaults to 4.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Limits the combined speed of the downloads. Unlimited by default.
+    pub fn max_bytes_per_second(mut self, bytes_per_second: u64) -> Self {
+        self.bytes_per_second = Some(bytes_per_second);
+        self
+    }
+
+    /// Reports the progress of the downloads to `observer`.
+    pub fn progress(mut self, observer: impl ProgressObserver + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Downloads every selected file that isn't installed yet, or whose
+    /// installed version has changed, then records them in the manifest.
+    ///
+    /// Fails without changing anything if a file would be written outside
+    /// the asset's directory. Files that couldn't be downloaded are reported
+    /// rather than failing the whole installation.
+    pub async fn run(self) -> Result<InstallReport> {
+        let Install { library, client, info, files, selection, .. } = self;
+        let planned = plan(info, files, &selection)?;
+        let installed = library.manifest.assets.get(&info.id);
+
+        let mut report = InstallReport::default();
+        let mut keep = Vec::new();
+        let mut jobs = Vec::new();
+        for (relative, entry, file) in planned {
+            let path = library.root.join(&relative);
+            let previous = installed.and_then(|asset| asset.files.get(&relative));
+            if previous.is_some_and(|previous| previous.md5.eq_ignore_ascii_case(&file.md5)) && path.exists() {
+                report.unchanged.push(relative.clone());
+                keep.push((relative, entry));
+                continue;
+            }
+            let actual = match file_md5(&path).await {
+                Ok(actual) => actual,
+                Err(err) => {
+                    report.failed.push((relative, Arc::new(err)));
+                    continue;
+                }
+            };
+            match actual {
+                // Not installed yet, or an intact older version that can be replaced.
+                None => jobs.push((relative, entry, file)),
+                Some(actual) if previous.is_some_and(|previous| previous.md5.eq_ignore_ascii_case(&actual)) => jobs.push((relative, entry, file)),
+                // Already there, just missing from the manifest.
+                Some(actual) if actual.eq_ignore_ascii_case(&file.md5) => {
+                    report.unchanged.push(relative.clone());
+                    keep.push((relative, entry));
+                },
+                Some(_) => report.conflicts.push(relative)
+            }
+        }
+
+        let mut batch = client
+            .download_batch(jobs.iter().map(|(relative, _, file)| (file.clone(), library.root.join(relative))))
+            .concurrency(self.concurrency);
+        if let Some(bytes_per_second) = self.bytes_per_second {
+            batch = batch.max_bytes_per_second(bytes_per_second);
+        }
+        if let Some(observer) = self.observer {
+            batch = batch.progress(move |event: &DownloadEvent, overall: &Progress| observer.on_event(event, overall));
+        }
+        let results = batch.run().await;
+        for ((relative, entry, _), job) in jobs.into_iter().zip(results.jobs) {
+            match job.result {
+                Ok(_) => {
+                    report.downloaded.push(relative.clone());
+                    keep.push((relative, entry));
+                },
+                Err(err) => report.failed.push((relative, err))
+            }
+        }
+
+        if !keep.is_empty() {
+            let asset = library.manifest.assets.entry(info.id.clone()).or_insert_with(|| LibraryAsset {
+                asset_type: files.asset_type(),
+                name: info.name.clone(),
+                date_published: info.date_published,
+                files: Default::default()
+            });
+            asset.name = info.name.clone();
+            asset.date_published = info.date_published;
+            asset.files.extend(keep);
+            library.save().await?;
+        }
+        Ok(report)
+    }
+}
+
+/// Every file of an asset that `selection` matches, by its path relative to
+/// the library root, with its manifest entry.
+pub(crate) fn plan(info: &AssetInfo, files: &Files, selection: &Selection) -> Result<Vec<(String, LibraryFile, FileData)>> {
+    let dir = asset_dir(files.asset_type(), &info.id)?;
+    let mut planned = Vec::new();
+    match files {
+        Files::HDRI(files) => {
+            for (resolution, formats) in &files.hdri {
+                for (format, file) in formats {
+                    add_package(&mut planned, &dir, selection, file, *resolution, &format.to_string(), None)?;
+                }
+            }
+        },
+        Files::Texture(files) => add_maps(&mut planned, &dir, selection, &[("blend", &files.blend), ("gltf", &files.gltf)], &files.maps)?,
+        Files::Model(files) => add_maps(&mut planned, &dir, selection, &[("blend", &files.blend), ("gltf", &files.gltf), ("fbx", &files.fbx)], &files.maps)?
+    }
+    Ok(planned)
+}
+
+fn add_maps(
+    planned: &mut Vec<(String, LibraryFile, FileData)>,
+    dir: &str,
+    selection: &Selection,
+    packages: &[(&str, &HashMap<Resolution, FileData>)],
+    maps: &TextureMaps
+) -> Result<()> {
+    for (format, files) in packages {
+        for (resolution, file) in *files {
+            add_package(planned, dir, selection, file, *resolution, format, None)?;
+        }
+    }
+    for (map, resolutions) in maps {
+        for (resolution, formats) in resolutions {
+            for (format, file) in formats {
+                add_package(planned, dir, selection, file, *resolution, &format.to_string(), Some(map))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds `file` and everything it includes, if `selection` matches it. Files
+/// that more than one package includes are only added once.
+fn add_package(
+    planned: &mut Vec<(String, LibraryFile, FileData)>,
+    dir: &str,
+    selection: &Selection,
+    file: &FileData,
+    resolution: Resolution,
+    format: &str,
+    map: Option<&TextureMap>
+) -> Result<()> {
+    if !selection.matches(resolution, format, map) {
+        return Ok(());
+    }
+    for (index, (path, file)) in download::package_files(file)?.into_iter().enumerate() {
+        let path = path.components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let relative = format!("{}/{}/{}", dir, resolution, path);
+        if planned.iter().any(|(existing, _, _)| *existing == relative) {
+            continue;
+        }
+        let entry = LibraryFile {
+            resolution,
+            format: format.to_string(),
+            map: map.filter(|_| index == 0).cloned(),
+            url: file.url.clone(),
+            md5: file.md5.clone(),
+            size: file.size
+        };
+        let file = FileData { include: Default::default(), ..file.clone() };
+        planned.push((relative, entry, file));
+    }
+    Ok(())
+}
+
+/// The directory an asset's files go in, relative to the library root,
+/// refusing ids that aren't safe directory names.
+fn asset_dir(asset_type: AssetType, id: &str) -> Result<String> {
+    if !download::is_safe_id(id) {
+        return Err(Error::UnsafePath(id.to_string()));
+    }
+    Ok(format!("{}/{}", asset_type.api_name(), id))
+}
+
+/// The MD5 of the file at `path`, or `None` if there's no file there.
+async fn file_md5(path: &Path) -> Result<Option<String>> {
+    match tokio::fs::metadata(path).await {
+        Ok(_) => Ok(Some(format!("{:x}", download::hash_file(path).await?.finalize()))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::io(path, err))
+    }
+}
+
+/// Removes the directories between `path` and `root` that are now empty.
+async fn remove_empty_dirs(root: &Path, path: &Path) {
+    for dir in path.ancestors().skip(1).take_while(|dir| *dir != root && dir.starts_with(root)) {
+        if tokio::fs::remove_dir(dir).await.is_err() {
+            break;
+        }
+    }
+}
+
+impl<T> fmt::Debug for Install<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Install")
+            .field("library", &self.library.root)
+            .field("id", &self.info.id)
+            .field("selection", &self.selection)
+            .finish_non_exhaustive()
+    }
+}
\ No newline at end of file
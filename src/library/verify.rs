@@ -0,0 +1,270 @@
+use std::{collections::HashSet, fmt, fs, io::{self, Read}, path::{Path, PathBuf}, sync::{Arc, Mutex}, thread};
+
+use futures_util::{stream, StreamExt};
+use md5::{Digest, Md5};
+
+use crate::{data::{asset::AssetType, files::FileData}, download::Batch, transport::Transport, Client, Error, Result};
+
+use super::{Library, LibraryFile};
+
+/// How much of a file is read at a time while hashing, which is also how
+/// often progress is reported.
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+type Observer = Arc<dyn Fn(&VerifyProgress) + Send + Sync>;
+
+/// A check of every file in a library, started with `Library::verify`.
+pub struct Verify<'a> {
+    library: &'a Library,
+    concurrency: usize,
+    observer: Option<Observer>
+}
+
+/// The overall progress of a `Verify`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerifyProgress {
+    /// How many files the manifest has.
+    pub files: usize,
+    pub files_checked: usize,
+    pub bytes_checked: u64,
+    /// The size of every file in the manifest, added up.
+    pub bytes_total: u64
+}
+
+/// The problems `Verify::run` found, by path relative to the library root.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// How many files were checked.
+    pub checked: usize,
+    /// Files in the manifest that don't exist.
+    pub missing: Vec<String>,
+    /// Files that don't match their size or MD5 in the manifest.
+    pub corrupted: Vec<Corrupted>,
+    /// Files in an asset type's directory that aren't in the manifest, such
+    /// as leftover `.part` files.
+    pub extra: Vec<String>,
+    /// Files that couldn't be read.
+    pub errors: Vec<(String, Error)>
+}
+
+/// A file that doesn't match the manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corrupted {
+    pub path: String,
+    pub size: u64,
+    /// The file's MD5, if it was hashed. Files of the wrong size aren't.
+    pub md5: Option<String>
+}
+
+impl VerifyReport {
+    /// Whether every file in the manifest is intact, and nothing else is in
+    /// the library.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupted.is_empty() && self.extra.is_empty() && self.errors.is_empty()
+    }
+}
+
+enum Check {
+    Intact,
+    Missing,
+    Corrupted(Corrupted)
+}
+
+impl Library {
+    /// Starts checking every file in the manifest against its size and MD5,
+    /// and looking for files that aren't in the manifest.
+    pub fn verify(&self) -> Verify<'_> {
+        Verify {
+            library: self,
+            concurrency: thread::available_parallelism().map_or(4, |threads| threads.get()),
+            observer: None
+        }
+    }
+
+    /// Starts downloading the missing and corrupted files in `report` again,
+    /// replacing the corrupted ones. Other files are left alone, and the
+    /// manifest isn't changed.
+    pub fn repair<'a, T: Transport>(&self, client: &'a Client<T>, report: &VerifyReport) -> Batch<'a, T> {
+        let broken = report.missing.iter().chain(report.corrupted.iter().map(|corrupted| &corrupted.path));
+        let jobs = broken
+            .filter_map(|path| {
+                let file = self.manifest.assets.values().find_map(|asset| asset.files.get(path))?;
+                let data = FileData { url: file.url.clone(), md5: file.md5.clone(), size: file.size, include: Default::default() };
+                Some((data, self.root.join(path)))
+            })
+            .collect::<Vec<_>>();
+        client.download_batch(jobs)
+    }
+}
+
+impl Verify<'_> {
+    /// Sets how many files are hashed at once. Defaults to the number of
+    /// CPUs; on network drives, more may be faster.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Calls `observer` with the overall progress as files are hashed. It's
+    /// called from the threads doing the hashing, so it should return quickly.
+    pub fn progress(mut self, observer: impl Fn(&VerifyProgress) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    pub async fn run(self) -> Result<VerifyReport> {
+        let library = self.library;
+        let files = library.manifest.assets.values()
+            .flat_map(|asset| asset.files.iter())
+            .map(|(path, file)| (path.clone(), file.clone()))
+            .collect::<Vec<_>>();
+        let progress = Arc::new(Mutex::new(VerifyProgress {
+            files: files.len(),
+            bytes_total: files.iter().map(|(_, file)| file.size).sum(),
+            ..Default::default()
+        }));
+        let reporter = Reporter { progress, observer: self.observer };
+
+        let root = library.root.clone();
+        let known = files.iter().map(|(path, _)| path.clone()).collect::<HashSet<_>>();
+        let extra = tokio::task::spawn_blocking(move || find_extra(&root, &known));
+
+        let mut checks = stream::iter(files)
+            .map(|(relative, file)| {
+                let path = library.root.join(&relative);
+                let reporter = reporter.clone();
+                let name = relative.clone();
+                let check = tokio::task::spawn_blocking(move || check(&path, &name, &file, &reporter));
+                async move { (relative, check.await.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))) }
+            })
+            .buffer_unordered(self.concurrency);
+
+        let mut report = VerifyReport::default();
+        while let Some((relative, check)) = checks.next().await {
+            report.checked += 1;
+            match check {
+                Ok(Check::Intact) => {},
+                Ok(Check::Missing) => report.missing.push(relative),
+                Ok(Check::Corrupted(corrupted)) => report.corrupted.push(corrupted),
+                Err(err) => report.errors.push((relative, err))
+            }
+        }
+        report.extra = extra.await.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))?;
+        report.missing.sort();
+        report.corrupted.sort_by(|a, b| a.path.cmp(&b.path));
+        report.errors.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(report)
+    }
+}
+
+/// Shares the overall progress between the hashing threads.
+#[derive(Clone)]
+struct Reporter {
+    progress: Arc<Mutex<VerifyProgress>>,
+    observer: Option<Observer>
+}
+
+impl Reporter {
+    fn report(&self, bytes: u64, finished: bool) {
+        let progress = {
+            let mut progress = self.progress.lock().unwrap();
+            progress.bytes_checked += bytes;
+            progress.files_checked += finished as usize;
+            *progress
+        };
+        if let Some(observer) = &self.observer {
+            observer(&progress);
+        }
+    }
+}
+
+/// Checks the file at `path` against its manifest entry, which is at
+/// `relative` in the manifest.
+fn check(path: &Path, relative: &str, file: &LibraryFile, reporter: &Reporter) -> Result<Check> {
+    let result = hash(path, relative, file, reporter);
+    // Count what wasn't read of missing and corrupted files as checked, so
+    // the total still adds up.
+    let unread = match &result {
+        Ok((_, read)) => file.size.saturating_sub(*read),
+        Err(_) => 0
+    };
+    reporter.report(unread, true);
+    let (check, _) = result?;
+    Ok(check)
+}
+
+/// Returns the result of the check and how many bytes were read.
+fn hash(path: &Path, relative: &str, file: &LibraryFile, reporter: &Reporter) -> Result<(Check, u64)> {
+    let mut handle = match fs::File::open(path) {
+        Ok(handle) => handle,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok((Check::Missing, 0)),
+        Err(err) => return Err(Error::io(path, err))
+    };
+    let size = handle.metadata().map_err(|err| Error::io(path, err))?.len();
+    if size != file.size {
+        return Ok((Check::Corrupted(Corrupted { path: relative.to_string(), size, md5: None }), 0));
+    }
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut read_total = 0;
+    loop {
+        let read = handle.read(&mut buffer).map_err(|err| Error::io(path, err))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        read_total += read as u64;
+        reporter.report(read as u64, false);
+    }
+    let md5 = format!("{:x}", hasher.finalize());
+    if read_total == file.size && md5.eq_ignore_ascii_case(&file.md5) {
+        Ok((Check::Intact, read_total))
+    } else {
+        Ok((Check::Corrupted(Corrupted { path: relative.to_string(), size: read_total, md5: Some(md5) }), read_total))
+    }
+}
+
+/// Every file under the asset type directories of `root` that isn't in
+/// `known`, relative to `root` with `/` separators.
+fn find_extra(root: &Path, known: &HashSet<String>) -> Result<Vec<String>> {
+    let mut extra = Vec::new();
+    let mut dirs = [AssetType::HDRI, AssetType::Texture, AssetType::Model]
+        .iter()
+        .map(|asset_type| PathBuf::from(asset_type.api_name()))
+        .collect::<Vec<_>>();
+    while let Some(dir) = dirs.pop() {
+        let absolute = root.join(&dir);
+        let entries = match fs::read_dir(&absolute) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(Error::io(&absolute, err))
+        };
+        for entry in entries {
+            let entry = entry.map_err(|err| Error::io(&absolute, err))?;
+            let relative = dir.join(entry.file_name());
+            let file_type = entry.file_type().map_err(|err| Error::io(&entry.path(), err))?;
+            if file_type.is_dir() {
+                dirs.push(relative);
+                continue;
+            }
+            let relative = relative.components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if !known.contains(&relative) {
+                extra.push(relative);
+            }
+        }
+    }
+    extra.sort();
+    Ok(extra)
+}
+
+impl fmt::Debug for Verify<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Verify")
+            .field("library", &self.library.root)
+            .field("concurrency", &self.concurrency)
+            .finish_non_exhaustive()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,107 @@
+use std::fmt;
+
+use crate::{Error, Result};
+
+/// How to handle API data that doesn't match what this crate expects, such as
+/// an unparseable resolution or an unknown asset type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Fail with `Error::InvalidData` on the first problem.
+    Strict,
+    /// Skip or default whatever couldn't be parsed and report it as a
+    /// `ParseWarning`.
+    #[default]
+    Lenient
+}
+
+/// Something in an API response that couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    /// The id of the asset the data belongs to.
+    pub asset: String,
+    /// The path of the offending field, e.g. `hdri.4x` or `dimensions`.
+    pub field: String,
+    pub message: String
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "asset `{}`, field `{}`: {}", self.asset, self.field, self.message)
+    }
+}
+
+/// A value parsed in lenient mode, together with everything that had to be
+/// skipped or defaulted to produce it.
+#[derive(Debug, Clone)]
+pub struct Parsed<T> {
+    pub value: T,
+    pub warnings: Vec<ParseWarning>
+}
+
+impl<T> Parsed<T> {
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Parsed<U> {
+        Parsed {
+            value: f(self.value),
+            warnings: self.warnings
+        }
+    }
+}
+
+/// Tracks problems found while converting API JSON into `data` types.
+#[derive(Debug)]
+pub struct ParseContext {
+    mode: ParseMode,
+    asset: String,
+    warnings: Vec<ParseWarning>
+}
+
+impl ParseContext {
+    pub fn new(mode: ParseMode, asset: impl Into<String>) -> Self {
+        Self {
+            mode,
+            asset: asset.into(),
+            warnings: Vec::new()
+        }
+    }
+
+    pub fn mode(&self) -> ParseMode {
+        self.mode
+    }
+
+    /// Sets the asset that subsequent warnings are reported against.
+    pub fn set_asset(&mut self, asset: impl Into<String>) {
+        self.asset = asset.into();
+    }
+
+    /// Reports a problem with `field` of the current asset. Fails in strict
+    /// mode, otherwise records a warning so the caller can carry on.
+    pub fn warn(&mut self, field: impl Into<String>, message: impl Into<String>) -> Result<()> {
+        let warning = ParseWarning {
+            asset: self.asset.clone(),
+            field: field.into(),
+            message: message.into()
+        };
+        match self.mode {
+            ParseMode::Strict => Err(Error::InvalidData(warning)),
+            ParseMode::Lenient => {
+                self.warnings.push(warning);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    pub fn finish<T>(self, value: T) -> Parsed<T> {
+        Parsed {
+            value,
+            warnings: self.warnings
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
+use crate::{data::{self, asset::AssetType}, Client, Result};
 
-use crate::{data::{self, asset::AssetType}, json};
+use super::query::Query;
 
 pub struct Params {
     pub asset_type: Option<AssetType>,
@@ -12,50 +12,24 @@ pub struct Params {
 }
 
 impl Params {
-    pub fn as_query_params(&self) -> String {
-        let mut params = HashMap::new();
-
-        if !self.categories.is_empty() {
-            params.insert("categories", self.categories.iter()
-                .map(|x| x.to_string())
-                .reduce(|a, b| format!("{},{}", a, b))
-                .unwrap()
-            );
-        }
-        if !self.search.is_empty() {
-            params.insert("search", self.search.iter()
-                .map(|x| x.to_string())
-                .reduce(|a, b| format!("{},{}", a, b))
-                .unwrap()
-            );
-        }
-
+    pub fn query(&self) -> Query {
+        let mut query = Query::new();
+        query.set_list("categories", &self.categories);
+        query.set_list("search", &self.search);
         if let Some(asset_type) = &self.asset_type {
-            params.insert("type", match asset_type {
-                AssetType::HDRI => "hdris".to_string(),
-                AssetType::Model => "models".to_string(),
-                AssetType::Texture => "textures".to_string()
-            });
+            query.set("type", asset_type.api_name());
         }
         if let Some(author) = &self.author {
-            params.insert("author", author.to_string());
+            query.set("author", author.as_str());
         }
-        
-        let query_params = params.into_iter()
-            .map(|(key, value)| format!("{}={}", key, value))
-            .reduce(|a, b| format!("{}&{}", a, b))
-            .unwrap_or_default();
+        query
+    }
 
-        query_params
+    pub fn as_query_params(&self) -> String {
+        self.query().to_string()
     }
 }
 
 pub async fn get(params: Params) -> Result<HashMap<String, data::asset::AssetInfo>> {
-    let url = format!("https://api.polyhaven.com/assets?{}", params.as_query_params());
-    let resp = reqwest::get(url).await?.json::<HashMap<String, json::asset::AssetInfo>>().await?;
-    Ok(
-        resp.into_iter()
-        .map(|(id, json)| (id.to_string(), data::asset::AssetInfo::from_json(json, id)))
-        .collect()
-    )
+    Client::shared().assets(&params).await
 }
\ No newline at end of file
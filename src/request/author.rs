@@ -1,9 +1,5 @@
-use anyhow::Result;
-
-use crate::{data, json};
+use crate::{data, Client, Result};
 
 pub async fn get(id: &str) -> Result<data::author::Author> {
-    let url = format!("https://api.polyhaven.com/author/{}", id);
-    let resp = reqwest::get(url).await?.json::<json::author::Author>().await?;
-    Ok(resp.into())
+    Client::shared().author(id).await
 }
\ No newline at end of file
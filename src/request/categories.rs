@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
+use crate::{data::asset::AssetType, Client, Result};
 
-use crate::data::asset::AssetType;
+use super::query::Query;
 
 pub struct Params {
     pub asset_type: AssetType,
@@ -10,33 +10,17 @@ pub struct Params {
 }
 
 impl Params {
-    pub fn as_query_params(&self) -> String {
-        let mut params = HashMap::new();
-
-        if !self.in_categories.is_empty() {
-            params.insert("in", self.in_categories.iter()
-                .map(|x| x.to_string())
-                .reduce(|a, b| format!("{},{}", a, b))
-                .unwrap()
-            );
-        }
-
-        let query_params = params.into_iter()
-            .map(|(key, value)| format!("{}={}", key, value))
-            .reduce(|a, b| format!("{}&{}", a, b))
-            .unwrap_or_default();
+    pub fn query(&self) -> Query {
+        let mut query = Query::new();
+        query.set_list("in", &self.in_categories);
+        query
+    }
 
-        query_params
+    pub fn as_query_params(&self) -> String {
+        self.query().to_string()
     }
 }
 
 pub async fn categories(params: Params) -> Result<HashMap<String, u32>> {
-    let asset_type = match params.asset_type {
-        AssetType::HDRI => "hdris",
-        AssetType::Texture => "textures",
-        AssetType::Model => "models",
-    };
-    let url = format!("https://api.polyhaven.com/categories/{}?{}", asset_type, params.as_query_params());
-    let resp = reqwest::get(url).await?.json::<HashMap<String, u32>>().await?;
-    Ok(resp)
+    Client::shared().categories(&params).await
 }
\ No newline at end of file
@@ -1,5 +1,21 @@
+//! Free functions for each endpoint, sent through a shared client.
+//!
+//! The shared client has the default configuration unless `set_client` is
+//! called first. Use a `Client` directly for anything else.
+
+use crate::{transport::Transport, Client};
+
 pub mod assets;
 pub mod info;
 pub mod files;
 pub mod author;
-pub mod categories;
\ No newline at end of file
+pub mod categories;
+pub mod query;
+
+/// Sets the client used by the free functions in this module, for example
+/// an offline client from `Client::offline`. This must be called before any
+/// of them are; returns `false`, leaving the client unchanged, if the shared
+/// client is already in use.
+pub fn set_client<T: Transport>(client: Client<T>) -> bool {
+    Client::set_shared(client.into_dyn())
+}
\ No newline at end of file
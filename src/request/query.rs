@@ -0,0 +1,68 @@
+use std::{collections::BTreeMap, fmt};
+
+use url::{form_urlencoded, Url};
+
+/// The query parameters of an API request.
+///
+/// Keys are kept sorted, so the same parameters always produce the same URL
+/// and URLs can be used as cache keys. Values are percent-encoded when the
+/// query is written out, so they may contain any characters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    params: BTreeMap<&'static str, String>
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, key: &'static str, value: impl Into<String>) -> &mut Self {
+        self.params.insert(key, value.into());
+        self
+    }
+
+    /// Sets a comma-separated list parameter. Does nothing if `values` is
+    /// empty, since the API treats an empty list the same as no list.
+    pub fn set_list<S: AsRef<str>>(&mut self, key: &'static str, values: &[S]) -> &mut Self {
+        if !values.is_empty() {
+            let joined = values.iter()
+                .map(|value| value.as_ref())
+                .collect::<Vec<_>>()
+                .join(",");
+            self.params.insert(key, joined);
+        }
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.params.get(key).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.params.iter().map(|(key, value)| (*key, value.as_str()))
+    }
+
+    /// Replaces the query of `url` with these parameters.
+    pub fn apply_to(&self, url: &mut Url) {
+        if self.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(self.iter());
+        }
+    }
+}
+
+/// Writes the percent-encoded query string, without a leading `?`.
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoded = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(self.iter())
+            .finish();
+        f.write_str(&encoded)
+    }
+}
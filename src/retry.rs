@@ -0,0 +1,175 @@
+use std::{future::Future, time::{Duration, SystemTime}};
+
+use reqwest::{header::{HeaderMap, RETRY_AFTER}, StatusCode};
+
+use crate::{transport::TransportErrorKind, Error, Result};
+
+/// Decides whether and when a failed request is tried again.
+///
+/// Delays grow exponentially from `base_delay`, doubling with each attempt up
+/// to `max_delay`. If the server sends a `Retry-After` header, that delay is
+/// used instead, even if it's longer than `max_delay`; only if it asks for
+/// longer than `max_retry_after` is the request not retried at all.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The most times a request is sent, including the first attempt. `1`
+    /// disables retrying.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// The longest `Retry-After` that will be waited for. Rate-limited APIs
+    /// commonly ask for a minute or more, so this is separate from
+    /// `max_delay`.
+    pub max_retry_after: Duration,
+    /// Randomises each delay to between half and all of its computed value,
+    /// so that many clients failing at once don't all retry at once.
+    pub jitter: bool,
+    /// Response statuses that are worth retrying.
+    pub retry_statuses: Vec<StatusCode>,
+    /// Retry when a connection to the server couldn't be established.
+    pub retry_connect: bool,
+    /// Retry when a request timed out.
+    pub retry_timeout: bool,
+    /// Retry when a connection failed part way through a request, for example
+    /// because it was reset while the response was being read.
+    pub retry_interrupted: bool
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Whether a request that failed with `error` may be retried, ignoring how
+    /// many attempts have been made.
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        match error {
+            Error::Status { status, .. } => self.retry_statuses.contains(status),
+            Error::Transport(err) => match err.kind() {
+                TransportErrorKind::Connect => self.retry_connect,
+                TransportErrorKind::Timeout => self.retry_timeout,
+                TransportErrorKind::Interrupted => self.retry_interrupted,
+                TransportErrorKind::Other => false
+            },
+            _ => false
+        }
+    }
+
+    /// How long to wait before sending attempt `attempt + 1`, after attempt
+    /// `attempt` (counting from 1) failed with `error`. Returns `None` if the
+    /// request shouldn't be retried.
+    pub fn delay(&self, attempt: u32, error: &Error) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.is_retryable(error) {
+            return None;
+        }
+        if let Error::Status { retry_after: Some(retry_after), .. } = error {
+            return (*retry_after <= self.max_retry_after).then_some(*retry_after);
+        }
+
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+        if self.jitter {
+            Some(delay.mul_f64(0.5 + fastrand::f64() * 0.5))
+        } else {
+            Some(delay)
+        }
+    }
+
+    /// Runs `request` until it succeeds or this policy gives up, logging each
+    /// failed attempt.
+    pub(crate) async fn run<T, F, Fut>(&self, description: &str, mut request: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>
+    {
+        let mut attempt = 1;
+        loop {
+            match request().await {
+                Ok(value) => return Ok(value),
+                Err(err) => match self.delay(attempt, &err) {
+                    Some(delay) => {
+                        log::warn!(
+                            "Attempt {}/{} of {} failed, retrying in {:?}: {}",
+                            attempt, self.max_attempts, description, delay, err
+                        );
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    },
+                    None => {
+                        if attempt > 1 {
+                            log::warn!("Giving up on {} after {} attempts: {}", description, attempt, err);
+                        }
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Like `run`, but sleeps the current thread between attempts.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn run_blocking<T>(&self, description: &str, mut request: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 1;
+        loop {
+            match request() {
+                Ok(value) => return Ok(value),
+                Err(err) => match self.delay(attempt, &err) {
+                    Some(delay) => {
+                        log::warn!(
+                            "Attempt {}/{} of {} failed, retrying in {:?}: {}",
+                            attempt, self.max_attempts, description, delay, err
+                        );
+                        std::thread::sleep(delay);
+                        attempt += 1;
+                    },
+                    None => {
+                        if attempt > 1 {
+                            log::warn!("Giving up on {} after {} attempts: {}", description, attempt, err);
+                        }
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts, backing off from half a second, retrying connection
+    /// failures, timeouts, 408, 429 and 5xx gateway/availability errors, and
+    /// waiting up to five minutes when asked to by `Retry-After`.
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            max_retry_after: Duration::from_secs(5 * 60),
+            jitter: true,
+            retry_statuses: vec![
+                StatusCode::REQUEST_TIMEOUT,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT
+            ],
+            retry_connect: true,
+            retry_timeout: true,
+            retry_interrupted: true
+        }
+    }
+}
+
+/// Reads a `Retry-After` header given either in seconds or as an HTTP date.
+pub(crate) fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
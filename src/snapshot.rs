@@ -0,0 +1,423 @@
+//! Catalog snapshots, for answering API queries without a network.
+//!
+//! A connected machine exports every asset, file listing, author and category
+//! count with `Snapshot::export`, and saves them as a single JSON file or a
+//! directory. Machines without network access load the snapshot and build a
+//! client with `Client::offline`, which answers every query from it, filtering
+//! `assets` and `categories` locally the same way the API does. To have the
+//! free functions in `request::*` use it too, pass it to `request::set_client`.
+
+use std::{collections::{BTreeMap, HashMap}, io, path::{Path, PathBuf}, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
+
+use bytes::Bytes;
+use futures_util::{stream, StreamExt};
+use percent_encoding::percent_decode_str;
+use reqwest::{header::{HeaderMap, HeaderValue, CONTENT_TYPE}, Method, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use crate::{data::asset::AssetType, download, request::{assets, categories}, transport::{BoxFuture, HttpRequest, HttpResponse, Transport, TransportError}, Client, Error, Result};
+
+/// The newest snapshot format this version of the crate can read.
+const FORMAT_VERSION: u32 = 1;
+
+/// How many requests `Snapshot::export` keeps in flight at once. The client's
+/// rate limit still applies on top of this.
+const EXPORT_CONCURRENCY: usize = 8;
+
+/// The raw API responses for the whole catalog.
+///
+/// Responses are kept as the JSON the API sent rather than parsed, so that a
+/// snapshot taken by one version of this crate can be served to another, and
+/// parse warnings are reported offline exactly as they would be online.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    version: u32,
+    /// When the snapshot was exported, in seconds since the Unix epoch.
+    pub exported_at: u64,
+    /// The API the snapshot was exported from.
+    pub api_url: String,
+    /// Every asset's `/info` response, by asset id.
+    pub assets: BTreeMap<String, Value>,
+    /// Every asset's `/files` response, by asset id.
+    pub files: BTreeMap<String, Value>,
+    /// Every author's `/author` response, by author id.
+    pub authors: BTreeMap<String, Value>,
+    /// The unfiltered `/categories` response for each asset type, keyed by
+    /// the type's `api_name`.
+    pub categories: BTreeMap<String, BTreeMap<String, u32>>
+}
+
+/// Everything in a snapshot directory's `snapshot.json`.
+#[derive(Serialize, Deserialize)]
+struct DirHeader {
+    version: u32,
+    exported_at: u64,
+    api_url: String,
+    categories: BTreeMap<String, BTreeMap<String, u32>>
+}
+
+impl Snapshot {
+    /// An empty snapshot, as if exported from `api_url` just now.
+    pub fn new(api_url: impl Into<String>) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            exported_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            api_url: api_url.into(),
+            assets: BTreeMap::new(),
+            files: BTreeMap::new(),
+            authors: BTreeMap::new(),
+            categories: BTreeMap::new()
+        }
+    }
+
+    /// Downloads the whole catalog through `client`.
+    ///
+    /// This makes one request per asset and per author, so it takes a few
+    /// minutes at the default rate limit. Assets whose files and authors whose
+    /// details can't be found are left out with a warning; any other error
+    /// fails the export.
+    pub async fn export<T: Transport>(client: &Client<T>) -> Result<Self> {
+        let mut snapshot = Self::new(client.api_url().as_str());
+
+        let params = assets::Params { asset_type: None, categories: vec![], author: None, search: vec![] };
+        snapshot.assets = client.get_json(&client.assets_url(&params)).await?;
+        log::info!("Exporting {} assets from {}", snapshot.assets.len(), client.api_url());
+
+        let ids = snapshot.assets.keys().cloned().collect::<Vec<_>>();
+        snapshot.files = fetch_all(ids, |id| client.files_url(id), client).await?;
+
+        snapshot.authors = fetch_all(snapshot.author_ids(), |id| client.author_url(id), client).await?;
+        snapshot.categories = fetch_categories(client).await?;
+        Ok(snapshot)
+    }
+
+    /// The ids of every author of an asset in the snapshot, sorted.
+    pub(crate) fn author_ids(&self) -> Vec<String> {
+        let mut author_ids = self.assets.values()
+            .filter_map(|asset| asset.get("authors")?.as_object())
+            .flat_map(|authors| authors.keys().cloned())
+            .collect::<Vec<_>>();
+        author_ids.sort();
+        author_ids.dedup();
+        author_ids
+    }
+
+    /// Loads a snapshot saved with either `save` or `save_dir`.
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let metadata = tokio::fs::metadata(path).await.map_err(|err| Error::io(path, err))?;
+        let snapshot: Self = if metadata.is_dir() {
+            let header: DirHeader = read_json(&path.join("snapshot.json")).await?;
+            Self {
+                version: header.version,
+                exported_at: header.exported_at,
+                api_url: header.api_url,
+                assets: read_json(&path.join("assets.json")).await?,
+                files: read_json_dir(&path.join("files")).await?,
+                authors: read_json_dir(&path.join("authors")).await?,
+                categories: header.categories
+            }
+        } else {
+            read_json(path).await?
+        };
+        if snapshot.version > FORMAT_VERSION {
+            return Err(Error::invalid_snapshot(path, format!(
+                "Format version {} is newer than this crate supports ({})",
+                snapshot.version, FORMAT_VERSION
+            )));
+        }
+        Ok(snapshot)
+    }
+
+    /// Saves the snapshot as a single JSON file.
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_json(path.as_ref(), self).await
+    }
+
+    /// Saves the snapshot as a directory, with one file per asset and author.
+    /// This is easier to inspect and to sync with tools like `rsync` than a
+    /// single file.
+    pub async fn save_dir(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        let header = DirHeader {
+            version: self.version,
+            exported_at: self.exported_at,
+            api_url: self.api_url.clone(),
+            categories: self.categories.clone()
+        };
+        write_json(&dir.join("snapshot.json"), &header).await?;
+        write_json(&dir.join("assets.json"), &self.assets).await?;
+        write_json_dir(&dir.join("files"), &self.files).await?;
+        write_json_dir(&dir.join("authors"), &self.authors).await
+    }
+
+    /// The ids of the assets that an `/assets` request with `query` returns.
+    ///
+    /// `type` (or `t`) may be an asset type's `api_name` or `all`. Assets
+    /// must be in every one of `categories` and be by `author`, if given.
+    /// Every comma-separated `search` term must appear in the asset's name,
+    /// tags or categories, ignoring case.
+    fn filter_assets(&self, query: &HashMap<String, String>) -> std::result::Result<BTreeMap<&str, &Value>, String> {
+        let asset_type = match query.get("type").or_else(|| query.get("t")).map(String::as_str) {
+            None | Some("all") => None,
+            Some(name) => Some(type_number(name).ok_or_else(|| format!("Invalid type `{}`", name))?)
+        };
+        let categories = list_param(query.get("categories"));
+        let search = list_param(query.get("search"));
+        let author = query.get("author");
+
+        let assets = self.assets.iter()
+            .filter(|(_, asset)| asset_type.is_none() || asset.get("type").and_then(Value::as_i64) == asset_type)
+            .filter(|(_, asset)| has_categories(asset, &categories))
+            .filter(|(_, asset)| match author {
+                Some(author) => asset.get("authors").and_then(|authors| authors.get(author)).is_some(),
+                None => true
+            })
+            .filter(|(_, asset)| search.iter().all(|term| {
+                let name = asset.get("name").and_then(Value::as_str).unwrap_or_default();
+                name.to_lowercase().contains(term)
+                    || strings(asset, "tags").chain(strings(asset, "categories")).any(|value| value.to_lowercase().contains(term))
+            }))
+            .map(|(id, asset)| (id.as_str(), asset))
+            .collect();
+        Ok(assets)
+    }
+
+    /// Counts the categories of assets of one type that are in every one of
+    /// `in_categories`, including an `all` count of the matching assets.
+    fn count_categories(&self, type_name: &str, in_categories: &[String]) -> std::result::Result<BTreeMap<String, u32>, String> {
+        let asset_type = match type_name {
+            "all" => None,
+            name => Some(type_number(name).ok_or_else(|| format!("Invalid type `{}`", name))?)
+        };
+        if in_categories.is_empty() {
+            if let Some(counts) = self.categories.get(type_name) {
+                return Ok(counts.clone());
+            }
+        }
+
+        let mut counts = BTreeMap::new();
+        let mut all = 0;
+        let matching = self.assets.values()
+            .filter(|asset| asset_type.is_none() || asset.get("type").and_then(Value::as_i64) == asset_type)
+            .filter(|asset| has_categories(asset, in_categories));
+        for asset in matching {
+            all += 1;
+            for category in strings(asset, "categories") {
+                *counts.entry(category.to_string()).or_insert(0) += 1;
+            }
+        }
+        counts.insert("all".to_string(), all);
+        Ok(counts)
+    }
+}
+
+/// Serves API requests from a `Snapshot`, without a network. Build a client
+/// with it using `Client::offline` or `ClientBuilder::build_offline`.
+///
+/// Requests for anything other than the snapshot's API, such as thumbnails
+/// or file downloads, get a 404.
+#[derive(Debug, Clone)]
+pub struct SnapshotTransport {
+    snapshot: Arc<Snapshot>,
+    api_url: Url
+}
+
+impl SnapshotTransport {
+    /// Serves `snapshot` as if it were the API at `api_url`.
+    pub fn new(snapshot: Snapshot, api_url: Url) -> Self {
+        Self {
+            snapshot: Arc::new(snapshot),
+            api_url
+        }
+    }
+
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    fn respond(&self, url: &Url) -> (StatusCode, Bytes) {
+        let Some(path) = self.api_path(url) else {
+            return not_found();
+        };
+        let query = url.query_pairs().into_owned().collect::<HashMap<_, _>>();
+        let snapshot = &self.snapshot;
+        let result = match path.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["assets"] => snapshot.filter_assets(&query).map(|assets| json_body(&assets)),
+            ["info", id] => return snapshot.assets.get(id).map_or_else(not_found, |asset| (StatusCode::OK, json_body(asset))),
+            ["files", id] => return snapshot.files.get(id).map_or_else(not_found, |files| (StatusCode::OK, json_body(files))),
+            ["author", id] => return snapshot.authors.get(id).map_or_else(not_found, |author| (StatusCode::OK, json_body(author))),
+            ["categories", type_name] => {
+                let in_categories = list_param(query.get("in"));
+                snapshot.count_categories(type_name, &in_categories).map(|counts| json_body(&counts))
+            },
+            _ => return not_found()
+        };
+        match result {
+            Ok(body) => (StatusCode::OK, body),
+            Err(message) => (StatusCode::BAD_REQUEST, json_body(&serde_json::json!({ "error": message })))
+        }
+    }
+
+    /// The decoded path segments of `url` after the API URL's own path, or
+    /// `None` if `url` isn't on the API.
+    fn api_path(&self, url: &Url) -> Option<Vec<String>> {
+        if url.origin() != self.api_url.origin() {
+            return None;
+        }
+        let decode = |segment: &str| percent_decode_str(segment).decode_utf8_lossy().into_owned();
+        let base = self.api_url.path_segments()?.filter(|segment| !segment.is_empty());
+        let mut path = url.path_segments()?.filter(|segment| !segment.is_empty());
+        for expected in base {
+            if path.next()? != expected {
+                return None;
+            }
+        }
+        Some(path.map(decode).collect())
+    }
+}
+
+impl Transport for SnapshotTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        let (status, body) = if request.method == Method::GET {
+            self.respond(&request.url)
+        } else {
+            (StatusCode::METHOD_NOT_ALLOWED, Bytes::new())
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        Box::pin(async move {
+            Ok(HttpResponse {
+                status,
+                headers,
+                body: body.into()
+            })
+        })
+    }
+}
+
+/// Fetches the JSON at `url(id)` for each of `ids`, skipping any that aren't
+/// found.
+pub(crate) async fn fetch_all<T: Transport>(ids: Vec<String>, url: impl Fn(&str) -> Url, client: &Client<T>) -> Result<BTreeMap<String, Value>> {
+    let mut responses = stream::iter(ids)
+        .map(|id| {
+            let url = url(&id);
+            async move { (id, client.get_json::<Value>(&url).await) }
+        })
+        .buffer_unordered(EXPORT_CONCURRENCY);
+
+    let mut values = BTreeMap::new();
+    while let Some((id, result)) = responses.next().await {
+        match result {
+            Ok(value) => {
+                values.insert(id, value);
+            },
+            Err(err @ Error::NotFound { .. }) => log::warn!("Leaving {} out of the snapshot: {}", id, err),
+            Err(err) => return Err(err)
+        }
+    }
+    Ok(values)
+}
+
+/// The unfiltered category counts of every asset type, keyed by `api_name`.
+pub(crate) async fn fetch_categories<T: Transport>(client: &Client<T>) -> Result<BTreeMap<String, BTreeMap<String, u32>>> {
+    let mut categories = BTreeMap::new();
+    for asset_type in [AssetType::HDRI, AssetType::Texture, AssetType::Model] {
+        let params = categories::Params { asset_type, in_categories: vec![] };
+        let counts = client.get_json(&client.categories_url(&params)).await?;
+        categories.insert(params.asset_type.api_name().to_string(), counts);
+    }
+    Ok(categories)
+}
+
+/// The numeric `type` the API uses for an asset type's `api_name`.
+fn type_number(api_name: &str) -> Option<i64> {
+    match api_name {
+        "hdris" => Some(0),
+        "textures" => Some(1),
+        "models" => Some(2),
+        _ => None
+    }
+}
+
+/// Splits a comma-separated query parameter into lowercase values.
+fn list_param(param: Option<&String>) -> Vec<String> {
+    param.map(|list| {
+        list.split(',')
+            .map(|value| value.trim().to_lowercase())
+            .filter(|value| !value.is_empty())
+            .collect()
+    }).unwrap_or_default()
+}
+
+fn strings<'a>(asset: &'a Value, key: &str) -> impl Iterator<Item = &'a str> {
+    asset.get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+/// Whether an asset is in every one of `categories`, which are lowercase.
+fn has_categories(asset: &Value, categories: &[String]) -> bool {
+    categories.iter().all(|wanted| strings(asset, "categories").any(|category| category.to_lowercase() == *wanted))
+}
+
+fn json_body(value: &impl Serialize) -> Bytes {
+    serde_json::to_vec(value).expect("JSON values always serialize").into()
+}
+
+fn not_found() -> (StatusCode, Bytes) {
+    (StatusCode::NOT_FOUND, Bytes::new())
+}
+
+async fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let bytes = tokio::fs::read(path).await.map_err(|err| Error::io(path, err))?;
+    let deserializer = &mut serde_json::Deserializer::from_slice(&bytes);
+    serde_path_to_error::deserialize(deserializer).map_err(|err| Error::invalid_snapshot(path, err.to_string()))
+}
+
+async fn read_json_dir(dir: &Path) -> Result<BTreeMap<String, Value>> {
+    let mut values = BTreeMap::new();
+    let mut read_dir = match tokio::fs::read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(values),
+        Err(err) => return Err(Error::io(dir, err))
+    };
+    while let Some(entry) = read_dir.next_entry().await.map_err(|err| Error::io(dir, err))? {
+        let path = entry.path();
+        let id = match (path.file_stem().and_then(|stem| stem.to_str()), path.extension()) {
+            (Some(id), Some(extension)) if extension == "json" => id.to_string(),
+            _ => continue
+        };
+        values.insert(id, read_json(&path).await?);
+    }
+    Ok(values)
+}
+
+async fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|err| Error::io(parent, err))?;
+    }
+    let bytes = serde_json::to_vec(value).map_err(|err| Error::invalid_snapshot(path, err.to_string()))?;
+    tokio::fs::write(path, bytes).await.map_err(|err| Error::io(path, err))
+}
+
+async fn write_json_dir(dir: &Path, values: &BTreeMap<String, Value>) -> Result<()> {
+    tokio::fs::create_dir_all(dir).await.map_err(|err| Error::io(dir, err))?;
+    for (id, value) in values {
+        write_json(&entry_path(dir, id)?, value).await?;
+    }
+    Ok(())
+}
+
+/// The file an asset or author is saved to in a snapshot directory, refusing
+/// ids that aren't safe file names.
+fn entry_path(dir: &Path, id: &str) -> Result<PathBuf> {
+    if !download::is_safe_id(id) {
+        return Err(Error::invalid_snapshot(dir, format!("Id `{}` can't be used as a file name", id)));
+    }
+    Ok(dir.join(format!("{}.json", id)))
+}
\ No newline at end of file
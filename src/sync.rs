@@ -0,0 +1,299 @@
+//! Incremental catalog syncs.
+//!
+//! A sync compares the catalog with a `Snapshot` saved by the previous sync,
+//! and returns both the differences and a new snapshot to save for the next
+//! one. Only one request is made for the asset list; the files of an asset
+//! are only fetched if it's new or its `date_published` has changed, since
+//! PolyHaven updates that when it re-releases an asset. Use
+//! `CatalogSync::recheck_files` to fetch every asset's files anyway.
+//!
+//! The differences can then be applied to a `Library` with `Library::apply`.
+
+use std::{collections::{BTreeMap, BTreeSet, HashSet}, fmt};
+
+use serde_json::Value;
+
+use crate::{library::{self, InstallReport, Library, RemoveReport, Selection}, request::assets, snapshot::{self, Snapshot}, transport::Transport, Client, Error, Result};
+
+/// Fields of an asset that change without it changing, so aren't reported.
+const IGNORED_FIELDS: &[&str] = &["download_count"];
+
+/// A sync against a previous snapshot, started with `Client::sync`.
+pub struct CatalogSync<'a, T> {
+    client: &'a Client<T>,
+    previous: &'a Snapshot,
+    recheck_files: bool
+}
+
+/// The result of a sync.
+#[derive(Debug, Clone)]
+pub struct Synced {
+    /// The catalog as it is now, with the files of unchanged assets carried
+    /// over from the previous snapshot. Save this for the next sync.
+    pub snapshot: Snapshot,
+    pub diff: CatalogDiff
+}
+
+/// The differences between two snapshots of the catalog. Every list is of
+/// asset ids, sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CatalogDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Assets that gained or lost a file, or whose files' MD5s changed.
+    pub files_changed: Vec<String>,
+    /// Assets whose details changed, such as their name, tags, categories or
+    /// authors. Download counts aren't compared.
+    pub metadata_changed: Vec<MetadataChange>
+}
+
+/// An asset whose details changed between snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataChange {
+    pub id: String,
+    /// The names of the fields that changed, as the API names them, like
+    /// `tags` or `date_published`.
+    pub fields: Vec<String>
+}
+
+impl<T: Transport> Client<T> {
+    /// Starts a sync against `previous`. For the first sync, pass an empty
+    /// `Snapshot::new`, and every asset will be reported as added.
+    pub fn sync<'a>(&'a self, previous: &'a Snapshot) -> CatalogSync<'a, T> {
+        CatalogSync {
+            client: self,
+            previous,
+            recheck_files: false
+        }
+    }
+}
+
+impl<T: Transport> CatalogSync<'_, T> {
+    /// Whether to fetch the files of every asset, not just new and
+    /// re-released ones. This makes one request per asset, but also catches
+    /// files that were replaced without the asset being re-released.
+    pub fn recheck_files(mut self, recheck_files: bool) -> Self {
+        self.recheck_files = recheck_files;
+        self
+    }
+
+    pub async fn run(self) -> Result<Synced> {
+        let CatalogSync { client, previous, recheck_files } = self;
+        let mut snapshot = Snapshot::new(client.api_url().as_str());
+
+        let params = assets::Params { asset_type: None, categories: vec![], author: None, search: vec![] };
+        snapshot.assets = client.get_json(&client.assets_url(&params)).await?;
+
+        let mut fetch = Vec::new();
+        for (id, asset) in &snapshot.assets {
+            let republished = previous.assets.get(id).map(|old| old.get("date_published")) != Some(asset.get("date_published"));
+            match previous.files.get(id) {
+                Some(files) if !recheck_files && !republished => {
+                    snapshot.files.insert(id.clone(), files.clone());
+                },
+                _ => fetch.push(id.clone())
+            }
+        }
+        log::info!("Syncing {} assets from {}, fetching files for {}", snapshot.assets.len(), client.api_url(), fetch.len());
+        snapshot.files.extend(snapshot::fetch_all(fetch, |id| client.files_url(id), client).await?);
+
+        let mut fetch = Vec::new();
+        for id in snapshot.author_ids() {
+            match previous.authors.get(&id) {
+                Some(author) => {
+                    snapshot.authors.insert(id, author.clone());
+                },
+                None => fetch.push(id)
+            }
+        }
+        snapshot.authors.extend(snapshot::fetch_all(fetch, |id| client.author_url(id), client).await?);
+        snapshot.categories = snapshot::fetch_categories(client).await?;
+
+        let diff = CatalogDiff::between(previous, &snapshot);
+        Ok(Synced { snapshot, diff })
+    }
+}
+
+impl CatalogDiff {
+    /// Compares the assets and files of two snapshots.
+    pub fn between(old: &Snapshot, new: &Snapshot) -> Self {
+        let mut diff = Self::default();
+        for (id, asset) in &new.assets {
+            let Some(old_asset) = old.assets.get(id) else {
+                diff.added.push(id.clone());
+                continue;
+            };
+            if file_md5s(old.files.get(id)) != file_md5s(new.files.get(id)) {
+                diff.files_changed.push(id.clone());
+            }
+            let fields = changed_fields(old_asset, asset);
+            if !fields.is_empty() {
+                diff.metadata_changed.push(MetadataChange { id: id.clone(), fields });
+            }
+        }
+        diff.removed = old.assets.keys()
+            .filter(|id| !new.assets.contains_key(*id))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.files_changed.is_empty() && self.metadata_changed.is_empty()
+    }
+
+    /// The assets that exist in both snapshots but changed in any way, sorted.
+    pub fn changed(&self) -> Vec<&str> {
+        let changed = self.files_changed.iter()
+            .chain(self.metadata_changed.iter().map(|change| &change.id))
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>();
+        changed.into_iter().collect()
+    }
+}
+
+/// How `Library::apply` updates a library.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApplyOptions {
+    /// Which files of each asset to install.
+    pub selection: Selection,
+    /// Whether to install added assets. Otherwise only assets already in the
+    /// library are updated.
+    pub install_added: bool,
+    /// Whether to remove assets that were removed from the catalog.
+    pub remove_removed: bool
+}
+
+/// What `Library::apply` did, by asset id.
+#[derive(Debug, Default)]
+pub struct ApplyReport {
+    pub installed: BTreeMap<String, InstallReport>,
+    /// Assets removed from the library, including files that were dropped
+    /// from a changed asset.
+    pub removed: BTreeMap<String, RemoveReport>,
+    /// Assets that couldn't be looked up in the snapshot or installed.
+    pub failed: Vec<(String, Error)>
+}
+
+impl ApplyReport {
+    /// Whether every asset was updated without failures or conflicts.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+            && self.installed.values().all(InstallReport::is_success)
+            && self.removed.values().all(RemoveReport::is_success)
+    }
+}
+
+impl Library {
+    /// Applies a sync's `diff` to this library, downloading files through
+    /// `client`. Assets' details and files are read from the sync's
+    /// `snapshot`.
+    ///
+    /// Changed assets that are in the library are installed again with
+    /// `options.selection`, which downloads only the files that changed and
+    /// records the new details. Files that are no longer in an asset's
+    /// listing are removed. As with `remove`, files that don't match the
+    /// manifest are never overwritten or deleted.
+    pub async fn apply<T: Transport>(&mut self, client: &Client<T>, snapshot: &Snapshot, diff: &CatalogDiff, options: &ApplyOptions) -> Result<ApplyReport> {
+        let mut report = ApplyReport::default();
+        if options.remove_removed {
+            for id in &diff.removed {
+                if self.asset(id).is_some() {
+                    let removed = self.remove(id, &Selection::default()).await?;
+                    report.removed.insert(id.clone(), removed);
+                }
+            }
+        }
+
+        let files_changed = diff.files_changed.iter().map(String::as_str).collect::<HashSet<_>>();
+        let added = diff.added.iter()
+            .filter(|_| options.install_added)
+            .map(String::as_str);
+        let changed = diff.changed().into_iter().filter(|id| self.asset(id).is_some());
+        let offline = Client::builder()
+            .parse_mode(client.parse_mode())
+            .build_offline(snapshot.clone())?;
+        for id in added.chain(changed).collect::<Vec<_>>() {
+            let info = match offline.info(id).await {
+                Ok(info) => info,
+                Err(err) => {
+                    report.failed.push((id.to_string(), err));
+                    continue;
+                }
+            };
+            let files = match offline.files_for(&info).await {
+                Ok(files) => files,
+                Err(err) => {
+                    report.failed.push((id.to_string(), err));
+                    continue;
+                }
+            };
+            if files_changed.contains(id) {
+                let listed = match library::plan(&info, &files, &Selection::default()) {
+                    Ok(planned) => planned.into_iter().map(|(path, _, _)| path).collect::<HashSet<_>>(),
+                    Err(err) => {
+                        report.failed.push((id.to_string(), err));
+                        continue;
+                    }
+                };
+                let removed = self.remove_where(id, |path, _| !listed.contains(path)).await?;
+                if !removed.removed.is_empty() || !removed.is_success() {
+                    report.removed.insert(id.to_string(), removed);
+                }
+            }
+            match self.install(client, &info, &files, &options.selection).run().await {
+                Ok(installed) => {
+                    report.installed.insert(id.to_string(), installed);
+                },
+                Err(err) => report.failed.push((id.to_string(), err))
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// The MD5 of every file in a `/files` response, by its path in the
+/// response, like `hdri/4k/exr`. A file is any object with a `url` and `md5`.
+fn file_md5s(files: Option<&Value>) -> Option<BTreeMap<String, &str>> {
+    fn collect<'a>(value: &'a Value, path: &mut Vec<&'a str>, md5s: &mut BTreeMap<String, &'a str>) {
+        let Some(object) = value.as_object() else {
+            return;
+        };
+        if let (Some(_), Some(md5)) = (object.get("url"), object.get("md5").and_then(Value::as_str)) {
+            md5s.insert(path.join("/"), md5);
+        }
+        for (key, child) in object {
+            path.push(key);
+            collect(child, path, md5s);
+            path.pop();
+        }
+    }
+
+    let mut md5s = BTreeMap::new();
+    collect(files?, &mut Vec::new(), &mut md5s);
+    Some(md5s)
+}
+
+/// The top-level fields that differ between two `/info` responses.
+fn changed_fields(old: &Value, new: &Value) -> Vec<String> {
+    let empty = serde_json::Map::new();
+    let old = old.as_object().unwrap_or(&empty);
+    let new = new.as_object().unwrap_or(&empty);
+    old.keys()
+        .chain(new.keys())
+        .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+        .filter(|field| old.get(*field) != new.get(*field))
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+impl<T> fmt::Debug for CatalogSync<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CatalogSync")
+            .field("previous_assets", &self.previous.assets.len())
+            .field("recheck_files", &self.recheck_files)
+            .finish_non_exhaustive()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use url::Url;
+
+/// Limits how quickly and how concurrently requests are sent to a host.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// How many requests may be started per second on average, or `None` for
+    /// no limit.
+    pub requests_per_second: Option<f64>,
+    /// How many requests may be started at once after a quiet period, before
+    /// `requests_per_second` kicks in.
+    pub burst: u32,
+    /// How many requests may be in flight at once, or `None` for no limit.
+    pub max_in_flight: Option<usize>
+}
+
+impl RateLimit {
+    pub fn unlimited() -> Self {
+        Self {
+            requests_per_second: None,
+            burst: 1,
+            max_in_flight: None
+        }
+    }
+
+    /// A limit of `requests` per second, with a burst of the same size.
+    pub fn per_second(requests: f64) -> Self {
+        Self {
+            requests_per_second: Some(requests),
+            burst: requests.ceil().max(1.0) as u32,
+            max_in_flight: None
+        }
+    }
+
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = Some(max_in_flight.max(1));
+        self
+    }
+}
+
+/// A token bucket, refilled continuously at `rate` tokens per second up to
+/// `capacity`.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    state: Mutex<(f64, Instant)>
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, capacity: f64) -> Self {
+        Self {
+            rate,
+            capacity,
+            state: Mutex::new((capacity, Instant::now()))
+        }
+    }
+
+    /// Takes `tokens` from the bucket, returning how long the caller must wait
+    /// before they are available. The tokens are reserved either way, so
+    /// callers queue up fairly behind each other.
+    pub fn reserve(&self, tokens: f64) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let (available, last_refill) = &mut *state;
+
+        let now = Instant::now();
+        *available = (*available + now.duration_since(*last_refill).as_secs_f64() * self.rate).min(self.capacity);
+        *last_refill = now;
+        *available -= tokens;
+
+        if *available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*available / self.rate)
+        }
+    }
+}
+
+/// Enforces one `RateLimit`.
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    bucket: Option<TokenBucket>,
+    in_flight: Option<Arc<Semaphore>>
+}
+
+impl Limiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            bucket: limit.requests_per_second
+                .filter(|rate| *rate > 0.0)
+                .map(|rate| TokenBucket::new(rate, limit.burst.max(1) as f64)),
+            in_flight: limit.max_in_flight.map(|max| Arc::new(Semaphore::new(max)))
+        }
+    }
+
+    /// Waits until a request may be sent. The request counts as in flight
+    /// until the returned permit is dropped.
+    pub async fn acquire(&self) -> Permit {
+        let permit = match &self.in_flight {
+            Some(semaphore) => Some(semaphore.clone().acquire_owned().await.expect("Limiter semaphores are never closed")),
+            None => None
+        };
+        if let Some(bucket) = &self.bucket {
+            let wait = bucket.reserve(1.0);
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+        Permit { _permit: permit }
+    }
+
+    /// Blocks the current thread until the rate limit allows a request. The
+    /// in-flight limit isn't applied, since it relies on the async runtime.
+    #[cfg(feature = "blocking")]
+    pub fn acquire_blocking(&self) {
+        if let Some(bucket) = &self.bucket {
+            let wait = bucket.reserve(1.0);
+            if !wait.is_zero() {
+                std::thread::sleep(wait);
+            }
+        }
+    }
+}
+
+/// Marks a request as in flight for as long as it's held.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    _permit: Option<OwnedSemaphorePermit>
+}
+
+/// Picks the `Limiter` for each request based on the host it's sent to.
+#[derive(Debug)]
+pub(crate) struct Throttle {
+    api_host: Option<String>,
+    api: Limiter,
+    cdn: Limiter,
+    hosts: HashMap<String, Limiter>
+}
+
+impl Throttle {
+    pub fn new(api_url: &Url, api: RateLimit, cdn: RateLimit, hosts: HashMap<String, RateLimit>) -> Self {
+        Self {
+            api_host: api_url.host_str().map(str::to_string),
+            api: Limiter::new(api),
+            cdn: Limiter::new(cdn),
+            hosts: hosts.into_iter()
+                .map(|(host, limit)| (host, Limiter::new(limit)))
+                .collect()
+        }
+    }
+
+    /// The limiter for `url`: its host's own limiter if one was configured,
+    /// otherwise the API limiter for the API host and the CDN limiter for
+    /// everything else.
+    pub fn limiter(&self, url: &Url) -> &Limiter {
+        let host = url.host_str();
+        if let Some(limiter) = host.and_then(|host| self.hosts.get(host)) {
+            limiter
+        } else if host.is_some() && host == self.api_host.as_deref() {
+            &self.api
+        } else {
+            &self.cdn
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::{collections::{HashMap, VecDeque}, error::Error as StdError, fmt, future::Future, pin::Pin, sync::{Arc, Mutex}};
+
+use bytes::{Bytes, BytesMut};
+use futures_util::{stream::{self, BoxStream}, StreamExt, TryStreamExt};
+use reqwest::{header::HeaderMap, Method, StatusCode};
+use url::Url;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Sends HTTP requests on behalf of a `Client`.
+///
+/// Implement this to use an HTTP stack other than `reqwest`, or to serve
+/// responses without a network in tests (see `MemoryTransport`). The client
+/// takes care of retries, rate limiting and status handling, so a transport
+/// only needs to deliver each request once and report what came back.
+pub trait Transport: Send + Sync + 'static {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>>;
+}
+
+impl Transport for Arc<dyn Transport> {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        (**self).send(request)
+    }
+}
+
+/// An HTTP request for a `Transport` to send.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap
+}
+
+impl HttpRequest {
+    pub fn get(url: Url) -> Self {
+        Self {
+            method: Method::GET,
+            url,
+            headers: HeaderMap::new()
+        }
+    }
+}
+
+/// The response to an `HttpRequest`. The body is streamed, so large files
+/// don't have to be held in memory.
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Body
+}
+
+/// A streamed response body.
+pub struct Body {
+    stream: BoxStream<'static, Result<Bytes, TransportError>>
+}
+
+impl Body {
+    pub fn empty() -> Self {
+        Self::from_stream(stream::empty())
+    }
+
+    pub fn from_stream(stream: impl futures_util::Stream<Item = Result<Bytes, TransportError>> + Send + 'static) -> Self {
+        Self { stream: stream.boxed() }
+    }
+
+    /// The next chunk of the body, or `None` once it has all been read.
+    pub async fn chunk(&mut self) -> Option<Result<Bytes, TransportError>> {
+        self.stream.next().await
+    }
+
+    /// Reads the rest of the body into memory.
+    pub async fn bytes(self) -> Result<Bytes, TransportError> {
+        let mut body = BytesMut::new();
+        let mut stream = self.stream;
+        while let Some(chunk) = stream.try_next().await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.freeze())
+    }
+}
+
+impl From<Bytes> for Body {
+    fn from(bytes: Bytes) -> Self {
+        Self::from_stream(stream::once(async move { Ok(bytes) }))
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes::from(bytes).into()
+    }
+}
+
+impl From<String> for Body {
+    fn from(text: String) -> Self {
+        Bytes::from(text).into()
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Body").finish_non_exhaustive()
+    }
+}
+
+/// What kind of failure a `TransportError` is, which decides whether a
+/// `RetryPolicy` retries it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportErrorKind {
+    /// A connection to the server couldn't be established.
+    Connect,
+    /// The request timed out.
+    Timeout,
+    /// The connection failed part way through the request or response.
+    Interrupted,
+    Other
+}
+
+/// A request that couldn't be sent, or a response that couldn't be read.
+#[derive(Debug, thiserror::Error)]
+#[error("{source}")]
+pub struct TransportError {
+    kind: TransportErrorKind,
+    #[source]
+    source: Box<dyn StdError + Send + Sync>
+}
+
+impl TransportError {
+    pub fn new(kind: TransportErrorKind, source: impl Into<Box<dyn StdError + Send + Sync>>) -> Self {
+        Self {
+            kind,
+            source: source.into()
+        }
+    }
+
+    pub fn kind(&self) -> TransportErrorKind {
+        self.kind
+    }
+}
+
+impl From<reqwest::Error> for TransportError {
+    fn from(err: reqwest::Error) -> Self {
+        let kind = if err.is_connect() {
+            TransportErrorKind::Connect
+        } else if err.is_timeout() {
+            TransportErrorKind::Timeout
+        } else if err.is_request() || err.is_body() {
+            TransportErrorKind::Interrupted
+        } else {
+            TransportErrorKind::Other
+        };
+        Self::new(kind, err)
+    }
+}
+
+/// The default transport, backed by a `reqwest::Client`.
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    http: reqwest::Client
+}
+
+impl ReqwestTransport {
+    pub fn new(http: reqwest::Client) -> Self {
+        Self { http }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        Box::pin(async move {
+            let resp = self.http.request(request.method, request.url)
+                .headers(request.headers)
+                .send()
+                .await?;
+            Ok(HttpResponse {
+                status: resp.status(),
+                headers: resp.headers().clone(),
+                body: Body::from_stream(resp.bytes_stream().map_err(TransportError::from))
+            })
+        })
+    }
+}
+
+/// A transport that serves canned responses from memory, keyed by URL, for
+/// testing code that uses a `Client` without a network.
+///
+/// Requests for URLs without a response get an empty 404. Every request is
+/// recorded and can be inspected with `requests`.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTransport {
+    responses: Arc<Mutex<HashMap<String, (StatusCode, Bytes)>>>,
+    queued: Arc<Mutex<HashMap<String, VecDeque<HttpResponse>>>>,
+    requests: Arc<Mutex<Vec<HttpRequest>>>
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves `json` with a 200 status for `url`.
+    pub fn with_json(self, url: &str, json: impl Into<String>) -> Self {
+        self.insert(url, StatusCode::OK, json.into());
+        self
+    }
+
+    /// Serves `body` with the given status for `url`, replacing any response
+    /// already set for it.
+    ///
+    /// # Panics
+    /// Panics if `url` isn't a valid URL.
+    pub fn insert(&self, url: &str, status: StatusCode, body: impl Into<Bytes>) {
+        let url = Url::parse(url).expect("MemoryTransport URLs must be valid");
+        self.responses.lock().unwrap().insert(url.to_string(), (status, body.into()));
+    }
+
+    /// Serves `response` to the next request for `url` only. Queued responses
+    /// are served in the order they were pushed, before the one set with
+    /// `insert`.
+    ///
+    /// Since the response is given whole, it can have headers, or a body that
+    /// fails part way through.
+    ///
+    /// # Panics
+    /// Panics if `url` isn't a valid URL.
+    pub fn push(&self, url: &str, response: HttpResponse) {
+        let url = Url::parse(url).expect("MemoryTransport URLs must be valid");
+        self.queued.lock().unwrap().entry(url.to_string()).or_default().push_back(response);
+    }
+
+    /// Every request sent so far, oldest first.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        let queued = self.queued.lock().unwrap()
+            .get_mut(request.url.as_str())
+            .and_then(|queue| queue.pop_front());
+        let response = queued.unwrap_or_else(|| {
+            let (status, body) = self.responses.lock().unwrap()
+                .get(request.url.as_str())
+                .cloned()
+                .unwrap_or((StatusCode::NOT_FOUND, Bytes::new()));
+            HttpResponse {
+                status,
+                headers: HeaderMap::new(),
+                body: body.into()
+            }
+        });
+        self.requests.lock().unwrap().push(request);
+        Box::pin(async move { Ok(response) })
+    }
+}
@@ -0,0 +1,134 @@
+use std::{collections::HashSet, path::Path, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use bytes::Bytes;
+use futures_util::stream;
+use md5::{Digest, Md5};
+use polyhaven::{
+    data::files::FileData,
+    download::{DownloadEvent, JobOutcome, Progress},
+    transport::{Body, HttpResponse, MemoryTransport},
+    Client, Error, RateLimit, RetryPolicy
+};
+use reqwest::{header::HeaderMap, StatusCode};
+
+fn file(name: &str, body: &[u8]) -> FileData {
+    let url = format!("https://dl.polyhaven.org/file/{}", name);
+    FileData { url, md5: format!("{:x}", Md5::digest(body)), size: body.len() as u64, include: Default::default() }
+}
+
+fn client(transport: MemoryTransport) -> Client<MemoryTransport> {
+    Client::builder()
+        .cdn_rate_limit(RateLimit::unlimited())
+        .retry_policy(RetryPolicy::none())
+        .build_with_transport(transport)
+        .unwrap()
+}
+
+fn urls(transport: &MemoryTransport) -> Vec<String> {
+    transport.requests().iter().map(|request| request.url.to_string()).collect()
+}
+
+fn read(path: &Path) -> Vec<u8> {
+    std::fs::read(path).unwrap()
+}
+
+#[tokio::test]
+async fn concurrency_limits_downloads_in_flight() {
+    let dir = tempfile::tempdir().unwrap();
+    let transport = MemoryTransport::new();
+    let mut jobs = Vec::new();
+    for i in 0..6 {
+        let body = format!("file number {}", i).into_bytes();
+        let file = file(&format!("{}.hdr", i), &body);
+        // Each body takes a while to arrive, so downloads overlap.
+        let chunk = stream::once(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(Bytes::from(body))
+        });
+        transport.push(&file.url, HttpResponse { status: StatusCode::OK, headers: HeaderMap::new(), body: Body::from_stream(chunk) });
+        jobs.push((file, dir.path().join(format!("{}.hdr", i))));
+    }
+
+    let active = Arc::new(Mutex::new((HashSet::new(), 0)));
+    let observed = active.clone();
+    let observer = move |event: &DownloadEvent, _: &Progress| {
+        let (urls, max) = &mut *observed.lock().unwrap();
+        match event {
+            DownloadEvent::Started { url, .. } => {
+                urls.insert(url.clone());
+            },
+            DownloadEvent::Finished { url } | DownloadEvent::Failed { url, .. } => {
+                urls.remove(url);
+            },
+            _ => {}
+        }
+        *max = (*max).max(urls.len());
+    };
+    let client = client(transport);
+    let report = client.download_batch(jobs).concurrency(2).progress(observer).run().await;
+
+    assert!(report.is_success());
+    assert_eq!(active.lock().unwrap().1, 2);
+    assert_eq!(client.transport().requests().len(), 6);
+}
+
+#[tokio::test]
+async fn same_file_is_downloaded_once_and_copied() {
+    let dir = tempfile::tempdir().unwrap();
+    let sky = file("sky_1k.hdr", b"sky");
+    let transport = MemoryTransport::new();
+    transport.insert(&sky.url, StatusCode::OK, &b"sky"[..]);
+    let (first, second) = (dir.path().join("a/sky_1k.hdr"), dir.path().join("b/sky_1k.hdr"));
+    let client = client(transport);
+    let report = client.download_batch([(sky.clone(), first.clone()), (sky.clone(), second.clone())]).run().await;
+
+    assert_eq!(report.jobs[0].result.as_ref().unwrap(), &JobOutcome::Downloaded);
+    assert_eq!(report.jobs[1].result.as_ref().unwrap(), &JobOutcome::Copied { from: first.clone() });
+    assert_eq!(read(&second), b"sky");
+    assert_eq!(urls(client.transport()), [sky.url]);
+}
+
+#[tokio::test]
+async fn shared_destination_is_only_written_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let sky = file("sky_1k.hdr", b"sky");
+    let other = file("other_1k.hdr", b"other");
+    let transport = MemoryTransport::new();
+    transport.insert(&sky.url, StatusCode::OK, &b"sky"[..]);
+    transport.insert(&other.url, StatusCode::OK, &b"other"[..]);
+    let path = dir.path().join("sky_1k.hdr");
+    let client = client(transport);
+    let jobs = [(sky.clone(), path.clone()), (sky.clone(), path.clone()), (other, path.clone())];
+    let report = client.download_batch(jobs).run().await;
+
+    assert_eq!(report.jobs[0].result.as_ref().unwrap(), &JobOutcome::Downloaded);
+    // The same file again is collapsed into the first job.
+    assert_eq!(report.jobs[1].result.as_ref().unwrap(), &JobOutcome::Downloaded);
+    // A different file is refused rather than overwriting it.
+    let err = report.jobs[2].result.as_ref().unwrap_err();
+    assert!(matches!(&**err, Error::DuplicateDestination { path: duplicate } if *duplicate == path), "{:?}", err);
+    assert_eq!(read(&path), b"sky");
+    assert_eq!(urls(client.transport()), [sky.url]);
+}
+
+#[tokio::test]
+async fn bandwidth_limit_is_shared_by_every_download() {
+    let dir = tempfile::tempdir().unwrap();
+    let transport = MemoryTransport::new();
+    let mut jobs = Vec::new();
+    for i in 0..3u8 {
+        let body = vec![i; 10_000];
+        let file = file(&format!("{}.hdr", i), &body);
+        transport.insert(&file.url, StatusCode::OK, body);
+        jobs.push((file, dir.path().join(format!("{}.hdr", i))));
+    }
+    let start = Instant::now();
+    // The first second's worth is allowed straight away, leaving 10,000
+    // bytes to wait half a second for.
+    let report = client(transport).download_batch(jobs).concurrency(3).max_bytes_per_second(20_000).run().await;
+
+    assert!(report.is_success());
+    let waited = start.elapsed();
+    assert!(waited >= Duration::from_millis(450), "waited {:?}", waited);
+    assert!(waited < Duration::from_secs(2), "waited {:?}", waited);
+}
\ No newline at end of file
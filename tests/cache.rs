@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use polyhaven::{
+    cache::{CacheStats, DiskCache},
+    data::asset::AssetType,
+    request::categories,
+    transport::{HttpResponse, MemoryTransport},
+    Client, RateLimit, RetryPolicy
+};
+use reqwest::{header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH}, StatusCode};
+
+const HDRIS: &str = "https://api.polyhaven.com/categories/hdris";
+const TEXTURES: &str = "https://api.polyhaven.com/categories/textures";
+const MODELS: &str = "https://api.polyhaven.com/categories/models";
+const BODY: &str = r#"{"all": 1}"#;
+
+fn client(transport: MemoryTransport, cache: DiskCache) -> Client<MemoryTransport> {
+    Client::builder()
+        .api_rate_limit(RateLimit::unlimited())
+        .retry_policy(RetryPolicy::none())
+        .cache(cache)
+        .build_with_transport(transport)
+        .unwrap()
+}
+
+fn serving(urls: &[&str]) -> MemoryTransport {
+    let transport = MemoryTransport::new();
+    for url in urls {
+        transport.insert(url, StatusCode::OK, BODY);
+    }
+    transport
+}
+
+async fn fetch(client: &Client<MemoryTransport>, asset_type: AssetType) -> u32 {
+    let params = categories::Params { asset_type, in_categories: vec![] };
+    client.categories(&params).await.unwrap()["all"]
+}
+
+fn stats(client: &Client<MemoryTransport>) -> CacheStats {
+    client.cache().unwrap().stats()
+}
+
+#[tokio::test]
+async fn fresh_response_is_a_hit() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = client(serving(&[HDRIS]), DiskCache::new(dir.path()));
+    assert_eq!(fetch(&client, AssetType::HDRI).await, 1);
+    assert_eq!(fetch(&client, AssetType::HDRI).await, 1);
+
+    assert_eq!(client.transport().requests().len(), 1);
+    assert_eq!(stats(&client), CacheStats { hits: 1, misses: 1, ..CacheStats::default() });
+}
+
+#[tokio::test]
+async fn uncached_response_is_a_miss() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = client(serving(&[HDRIS, TEXTURES]), DiskCache::new(dir.path()));
+    fetch(&client, AssetType::HDRI).await;
+    fetch(&client, AssetType::Texture).await;
+
+    assert_eq!(client.transport().requests().len(), 2);
+    assert_eq!(stats(&client), CacheStats { misses: 2, ..CacheStats::default() });
+    // Only the bodies and meta files are left, without any `.part` files.
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 4);
+}
+
+#[tokio::test]
+async fn stale_response_is_revalidated() {
+    let dir = tempfile::tempdir().unwrap();
+    let transport = serving(&[]);
+    let mut headers = HeaderMap::new();
+    headers.insert(ETAG, HeaderValue::from_static("\"v1\""));
+    transport.push(HDRIS, HttpResponse { status: StatusCode::OK, headers, body: String::from(BODY).into() });
+    transport.push(HDRIS, HttpResponse { status: StatusCode::NOT_MODIFIED, headers: HeaderMap::new(), body: Vec::new().into() });
+    let client = client(transport, DiskCache::new(dir.path()).ttl(Duration::ZERO));
+    fetch(&client, AssetType::HDRI).await;
+    // The 304 has no body, so this is served from the cache.
+    assert_eq!(fetch(&client, AssetType::HDRI).await, 1);
+
+    let requests = client.transport().requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].headers.get(IF_NONE_MATCH), None);
+    assert_eq!(requests[1].headers.get(IF_NONE_MATCH).unwrap(), "\"v1\"");
+    assert_eq!(stats(&client), CacheStats { misses: 1, revalidations: 1, ..CacheStats::default() });
+}
+
+#[tokio::test]
+async fn least_recently_used_response_is_evicted() {
+    let dir = tempfile::tempdir().unwrap();
+    // Room for two responses.
+    let cache = DiskCache::new(dir.path()).max_size(2 * BODY.len() as u64);
+    let client = client(serving(&[HDRIS, TEXTURES, MODELS]), cache);
+    fetch(&client, AssetType::HDRI).await;
+    fetch(&client, AssetType::Texture).await;
+    // Access times are in whole seconds, so wait for the next one before
+    // using the HDRIs again, leaving the textures least recently used.
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    fetch(&client, AssetType::HDRI).await;
+    fetch(&client, AssetType::Model).await;
+    assert_eq!(stats(&client).evictions, 1);
+
+    fetch(&client, AssetType::HDRI).await;
+    fetch(&client, AssetType::Texture).await;
+    let urls = client.transport().requests().iter()
+        .map(|request| request.url.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(urls, [HDRIS, TEXTURES, MODELS, TEXTURES]);
+}
\ No newline at end of file
@@ -0,0 +1,146 @@
+//! A minimal HTTP/1.1 server on localhost, for testing downloads through the
+//! real `reqwest` transport rather than a fake one.
+
+// Each test crate only uses some of this.
+#![allow(dead_code)]
+
+use std::{net::SocketAddr, sync::{Arc, Mutex}};
+
+use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader}, net::{TcpListener, TcpStream}};
+
+/// What the server received.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub path: String,
+    pub range: Option<String>
+}
+
+/// What the server sends back. Every response closes the connection.
+#[derive(Debug, Clone)]
+pub struct Reply {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+    content_length: bool,
+    cut_after: Option<usize>
+}
+
+impl Reply {
+    pub fn ok(body: &[u8]) -> Self {
+        Self { status: 200, headers: Vec::new(), body: body.to_vec(), content_length: true, cut_after: None }
+    }
+
+    /// A `206` with `body` from byte `start` on.
+    pub fn partial(body: &[u8], start: usize) -> Self {
+        let content_range = format!("bytes {}-{}/{}", start, body.len() - 1, body.len());
+        Self { status: 206, headers: vec![("Content-Range", content_range)], ..Self::ok(&body[start..]) }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self { status, ..Self::ok(b"") }
+    }
+
+    /// Leaves out the `Content-Length`, so the body ends when the connection
+    /// closes.
+    pub fn without_content_length(mut self) -> Self {
+        self.content_length = false;
+        self
+    }
+
+    /// Closes the connection after `bytes` of the body, while still
+    /// announcing all of it.
+    pub fn cut_after(mut self, bytes: usize) -> Self {
+        self.cut_after = Some(bytes);
+        self
+    }
+}
+
+pub struct Server {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Request>>>
+}
+
+impl Server {
+    /// Starts serving on a free port. `reply` is given each request along
+    /// with how many came before it.
+    pub async fn start(reply: impl Fn(usize, &Request) -> Reply + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let reply = Arc::new(reply);
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let reply = reply.clone();
+                let received = received.clone();
+                tokio::spawn(async move {
+                    let mut reader = BufReader::new(stream);
+                    let Some(request) = read_request(&mut reader).await else { return };
+                    let index = {
+                        let mut received = received.lock().unwrap();
+                        received.push(request.clone());
+                        received.len() - 1
+                    };
+                    respond(reader.into_inner(), reply(index, &request)).await;
+                });
+            }
+        });
+        Self { addr, requests }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// The `Range` header of every request so far, oldest first.
+    pub fn ranges(&self) -> Vec<Option<String>> {
+        self.requests.lock().unwrap().iter().map(|request| request.range.clone()).collect()
+    }
+}
+
+/// Reads a request's line and headers. Requests from the client never have
+/// a body, so that's everything.
+async fn read_request(reader: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.ok()?;
+    let path = line.split_whitespace().nth(1)?.to_string();
+    let mut range = None;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await.ok()?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("range") {
+                range = Some(value.trim().to_string());
+            }
+        }
+    }
+    Some(Request { path, range })
+}
+
+async fn respond(mut stream: TcpStream, reply: Reply) {
+    let reason = match reply.status {
+        200 => "OK",
+        206 => "Partial Content",
+        416 => "Range Not Satisfiable",
+        _ => "Unknown"
+    };
+    let mut head = format!("HTTP/1.1 {} {}\r\nConnection: close\r\n", reply.status, reason);
+    if reply.content_length {
+        head.push_str(&format!("Content-Length: {}\r\n", reply.body.len()));
+    }
+    for (name, value) in &reply.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let body = match reply.cut_after {
+        Some(bytes) => &reply.body[..bytes],
+        None => &reply.body[..]
+    };
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(body).await;
+    let _ = stream.shutdown().await;
+}
\ No newline at end of file
@@ -0,0 +1,321 @@
+use std::{path::Path, time::Duration};
+
+use bytes::Bytes;
+use futures_util::stream;
+use md5::{Digest, Md5};
+use polyhaven::{
+    data::files::FileData,
+    transport::{Body, HttpResponse, MemoryTransport, Transport, TransportError, TransportErrorKind},
+    Client, Error, RateLimit, RetryPolicy
+};
+use reqwest::{header::{HeaderMap, HeaderValue, CONTENT_RANGE, RANGE}, StatusCode};
+
+use self::common::{Reply, Server};
+
+mod common;
+
+const URL: &str = "https://dl.polyhaven.org/file/ph-assets/HDRIs/hdr/1k/sky_1k.hdr";
+const BODY: &[u8] = b"the whole of sky_1k.hdr";
+
+fn md5(bytes: &[u8]) -> String {
+    format!("{:x}", Md5::digest(bytes))
+}
+
+fn file(size: u64, md5: String) -> FileData {
+    FileData { url: URL.to_string(), md5, size, include: Default::default() }
+}
+
+fn client<T: Transport>(transport: T) -> Client<T> {
+    Client::builder()
+        .cdn_rate_limit(RateLimit::unlimited())
+        .retry_policy(RetryPolicy::none())
+        .build_with_transport(transport)
+        .unwrap()
+}
+
+fn serving(body: &[u8]) -> MemoryTransport {
+    let transport = MemoryTransport::new();
+    transport.insert(URL, StatusCode::OK, body.to_vec());
+    transport
+}
+
+fn assert_nothing_left(path: &Path) {
+    assert!(!path.exists(), "{} was left behind", path.display());
+    assert!(!path.with_file_name("sky_1k.hdr.part").exists(), "the .part file was left behind");
+}
+
+#[tokio::test]
+async fn verified_download_is_moved_into_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    let file = file(BODY.len() as u64, md5(BODY));
+    client(serving(BODY)).download(&file).to_path(&path).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), BODY);
+    assert!(!path.with_file_name("sky_1k.hdr.part").exists());
+}
+
+#[tokio::test]
+async fn body_larger_than_expected_is_a_size_mismatch() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    let expected = &BODY[..10];
+    let file = file(expected.len() as u64, md5(expected));
+    let err = client(serving(BODY)).download(&file).to_path(&path).await.unwrap_err();
+
+    assert!(matches!(err, Error::SizeMismatch { expected: 10, actual, .. } if actual == BODY.len() as u64), "{:?}", err);
+    assert_nothing_left(&path);
+}
+
+#[tokio::test]
+async fn wrong_hash_is_a_checksum_mismatch() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    let file = file(BODY.len() as u64, md5(b"something else"));
+    let err = client(serving(BODY)).download(&file).to_path(&path).await.unwrap_err();
+
+    assert!(matches!(&err, Error::ChecksumMismatch { actual, .. } if *actual == md5(BODY)), "{:?}", err);
+    assert_nothing_left(&path);
+}
+
+#[tokio::test]
+async fn failed_verification_replaces_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    std::fs::write(&path, b"an older download").unwrap();
+    let file = file(BODY.len() as u64, md5(b"something else"));
+    let err = client(serving(BODY)).download(&file).to_path(&path).await.unwrap_err();
+
+    assert!(matches!(err, Error::ChecksumMismatch { .. }), "{:?}", err);
+    assert_eq!(std::fs::read(&path).unwrap(), b"an older download");
+    assert!(!path.with_file_name("sky_1k.hdr.part").exists());
+}
+
+/// Serves `first_body`, cut off after `cut_after` bytes, to the first request,
+/// and `then` to the second. Anything after that gets the whole of `BODY`.
+fn interrupting(cut_after: usize, first_body: &'static [u8], then: HttpResponse) -> MemoryTransport {
+    let chunks = vec![
+        Ok(Bytes::from_static(&first_body[..cut_after])),
+        Err(TransportError::new(TransportErrorKind::Interrupted, "connection reset"))
+    ];
+    let transport = serving(BODY);
+    transport.push(URL, HttpResponse { status: StatusCode::OK, headers: HeaderMap::new(), body: Body::from_stream(stream::iter(chunks)) });
+    transport.push(URL, then);
+    transport
+}
+
+/// A `206` with the rest of `BODY` from `start`.
+fn partial(start: usize) -> HttpResponse {
+    let mut headers = HeaderMap::new();
+    let content_range = format!("bytes {}-{}/{}", start, BODY.len() - 1, BODY.len());
+    headers.insert(CONTENT_RANGE, HeaderValue::from_str(&content_range).unwrap());
+    HttpResponse { status: StatusCode::PARTIAL_CONTENT, headers, body: BODY[start..].to_vec().into() }
+}
+
+fn whole() -> HttpResponse {
+    HttpResponse { status: StatusCode::OK, headers: HeaderMap::new(), body: BODY.to_vec().into() }
+}
+
+fn ranges(transport: &MemoryTransport) -> Vec<Option<String>> {
+    transport.requests().iter()
+        .map(|request| request.headers.get(RANGE).map(|range| range.to_str().unwrap().to_string()))
+        .collect()
+}
+
+fn retrying<T: Transport>(transport: T) -> Client<T> {
+    let policy = RetryPolicy { base_delay: Duration::ZERO, jitter: false, ..RetryPolicy::default() };
+    Client::builder()
+        .cdn_rate_limit(RateLimit::unlimited())
+        .retry_policy(policy)
+        .build_with_transport(transport)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn interrupted_download_resumes_with_a_range_request() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    let file = file(BODY.len() as u64, md5(BODY));
+    let client = retrying(interrupting(10, BODY, partial(10)));
+    client.download(&file).to_path(&path).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), BODY);
+    assert_eq!(ranges(client.transport()), [None, Some("bytes=10-".to_string())]);
+    assert!(!path.with_file_name("sky_1k.hdr.part").exists());
+}
+
+#[tokio::test]
+async fn interrupted_download_restarts_if_the_server_ignores_ranges() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    let file = file(BODY.len() as u64, md5(BODY));
+    let client = retrying(interrupting(10, BODY, whole()));
+    client.download(&file).to_path(&path).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), BODY);
+    assert_eq!(ranges(client.transport()), [None, Some("bytes=10-".to_string())]);
+}
+
+#[tokio::test]
+async fn resumed_download_failing_verification_restarts_from_zero() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    let file = file(BODY.len() as u64, md5(BODY));
+    // The first ten bytes received are corrupt, so the resumed file's MD5
+    // won't match.
+    let client = retrying(interrupting(10, b"corrupted!rest is never sent", partial(10)));
+    client.download(&file).to_path(&path).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), BODY);
+    assert_eq!(ranges(client.transport()), [None, Some("bytes=10-".to_string()), None]);
+}
+
+/// A body big enough to arrive in several chunks.
+fn large_body() -> Vec<u8> {
+    (0..256 * 1024).map(|i| (i % 251) as u8).collect()
+}
+
+fn http_file(server: &Server, body: &[u8]) -> FileData {
+    FileData { url: server.url("/sky_1k.hdr"), md5: md5(body), size: body.len() as u64, include: Default::default() }
+}
+
+fn http_client(policy: RetryPolicy) -> Client {
+    Client::builder()
+        .cdn_rate_limit(RateLimit::unlimited())
+        .retry_policy(policy)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn download_streams_over_http() {
+    let body = large_body();
+    let served = body.clone();
+    let server = Server::start(move |_, _| Reply::ok(&served)).await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    http_client(RetryPolicy::none()).download(&http_file(&server, &body)).to_path(&path).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), body);
+    assert!(!path.with_file_name("sky_1k.hdr.part").exists());
+}
+
+#[tokio::test]
+async fn download_without_content_length_is_verified() {
+    let body = large_body();
+    let served = body.clone();
+    let server = Server::start(move |_, _| Reply::ok(&served).without_content_length()).await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    http_client(RetryPolicy::none()).download(&http_file(&server, &body)).to_path(&path).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), body);
+}
+
+#[tokio::test]
+async fn connection_closed_without_content_length_is_interrupted() {
+    let body = large_body();
+    let served = body.clone();
+    let server = Server::start(move |_, _| Reply::ok(&served).without_content_length().cut_after(1000)).await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    let err = http_client(RetryPolicy::none()).download(&http_file(&server, &body)).to_path(&path).await.unwrap_err();
+
+    assert!(matches!(&err, Error::Transport(err) if err.kind() == TransportErrorKind::Interrupted), "{:?}", err);
+    assert_eq!(std::fs::read(path.with_file_name("sky_1k.hdr.part")).unwrap(), &body[..1000]);
+}
+
+#[tokio::test]
+async fn mid_stream_error_keeps_the_part_file_until_resumed() {
+    let body = large_body();
+    let served = body.clone();
+    let server = Server::start(move |index, _| match index {
+        0 => Reply::ok(&served).cut_after(100_000),
+        _ => Reply::partial(&served, 100_000)
+    }).await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    let part = path.with_file_name("sky_1k.hdr.part");
+    let client = http_client(RetryPolicy::none());
+    let file = http_file(&server, &body);
+    let err = client.download(&file).to_path(&path).await.unwrap_err();
+
+    assert!(matches!(&err, Error::Transport(err) if err.kind() == TransportErrorKind::Interrupted), "{:?}", err);
+    assert!(!path.exists());
+    assert_eq!(std::fs::read(&part).unwrap(), &body[..100_000]);
+
+    client.download(&file).to_path(&path).await.unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), body);
+    assert!(!part.exists());
+}
+
+#[tokio::test]
+async fn oversized_http_body_removes_the_part_file() {
+    let body = large_body();
+    let served = body.clone();
+    let server = Server::start(move |_, _| Reply::ok(&served)).await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    let file = http_file(&server, &body[..1000]);
+    let err = http_client(RetryPolicy::none()).download(&file).to_path(&path).await.unwrap_err();
+
+    assert!(matches!(err, Error::SizeMismatch { expected: 1000, .. }), "{:?}", err);
+    assert_nothing_left(&path);
+}
+
+/// Serves `body`, cutting the first response off after 100,000 bytes, and
+/// answers later requests with `resumed`.
+async fn cut_then(body: &[u8], resumed: impl Fn(&[u8], Option<&str>) -> Reply + Send + Sync + 'static) -> Server {
+    let served = body.to_vec();
+    Server::start(move |index, request| match index {
+        0 => Reply::ok(&served).cut_after(100_000),
+        _ => resumed(&served, request.range.as_deref())
+    }).await
+}
+
+fn http_retrying() -> Client {
+    http_client(RetryPolicy { base_delay: Duration::ZERO, jitter: false, ..RetryPolicy::default() })
+}
+
+#[tokio::test]
+async fn range_answered_with_206_is_appended() {
+    let body = large_body();
+    let server = cut_then(&body, |body, range| match range {
+        Some("bytes=100000-") => Reply::partial(body, 100_000),
+        _ => Reply::ok(body)
+    }).await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    http_retrying().download(&http_file(&server, &body)).to_path(&path).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), body);
+    assert_eq!(server.ranges(), [None, Some("bytes=100000-".to_string())]);
+}
+
+#[tokio::test]
+async fn range_answered_with_200_restarts() {
+    let body = large_body();
+    let server = cut_then(&body, |body, _| Reply::ok(body)).await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    http_retrying().download(&http_file(&server, &body)).to_path(&path).await.unwrap();
+
+    // The whole body replaced the partial one, rather than being appended.
+    assert_eq!(std::fs::read(&path).unwrap(), body);
+    assert_eq!(server.ranges(), [None, Some("bytes=100000-".to_string())]);
+}
+
+#[tokio::test]
+async fn range_answered_with_416_restarts() {
+    let body = large_body();
+    let server = cut_then(&body, |body, range| match range {
+        Some(_) => Reply::status(416),
+        None => Reply::ok(body)
+    }).await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sky_1k.hdr");
+    http_retrying().download(&http_file(&server, &body)).to_path(&path).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), body);
+    assert_eq!(server.ranges(), [None, Some("bytes=100000-".to_string()), None]);
+}
\ No newline at end of file
@@ -0,0 +1,191 @@
+use std::{collections::HashMap, path::Path};
+
+use chrono::DateTime;
+use md5::{Digest, Md5};
+use polyhaven::{
+    data::{
+        asset::{Asset, AssetInfo, HDRIAsset},
+        files::{FileData, Files, HDRIFiles, HDRIFormat, Resolution}
+    },
+    library::{Library, Selection},
+    transport::MemoryTransport,
+    Client, Error, RateLimit, RetryPolicy
+};
+use reqwest::StatusCode;
+
+/// The files of the `sky` HDRI: each is its path in a library, its URL and
+/// its body.
+const FILES: [(&str, &str, &str); 3] = [
+    ("hdris/sky/1k/sky_1k.exr", "https://dl.polyhaven.org/file/ph-assets/HDRIs/exr/1k/sky_1k.exr", "1k exr"),
+    ("hdris/sky/1k/sky_1k.hdr", "https://dl.polyhaven.org/file/ph-assets/HDRIs/hdr/1k/sky_1k.hdr", "1k hdr"),
+    ("hdris/sky/2k/sky_2k.hdr", "https://dl.polyhaven.org/file/ph-assets/HDRIs/hdr/2k/sky_2k.hdr", "2k hdr")
+];
+
+fn info() -> AssetInfo {
+    AssetInfo {
+        id: "sky".to_string(),
+        name: "Sky".to_string(),
+        date_published: DateTime::from_timestamp(1_600_000_000, 0).unwrap(),
+        download_count: 10,
+        authors: HashMap::from([("Greg Zaal".to_string(), "All".to_string())]),
+        donated: false,
+        categories: vec!["outdoor".to_string()],
+        tags: vec![],
+        asset: Asset::HDRI(HDRIAsset { whitebalance: None, backplates: false, evs_cap: 12, coords: None })
+    }
+}
+
+fn files() -> Files {
+    let mut hdri = HashMap::<Resolution, HashMap<HDRIFormat, FileData>>::new();
+    for (path, url, body) in FILES {
+        let resolution = path.split('/').nth(2).unwrap().parse().unwrap();
+        let format = path.rsplit('.').next().unwrap().parse().unwrap();
+        let file = FileData { url: url.to_string(), md5: format!("{:x}", Md5::digest(body)), size: body.len() as u64, include: HashMap::new() };
+        hdri.entry(resolution).or_default().insert(format, file);
+    }
+    Files::HDRI(HDRIFiles { hdri, backplates: HashMap::new(), colorchart: None, tonemapped: None })
+}
+
+fn client() -> Client<MemoryTransport> {
+    let transport = MemoryTransport::new();
+    for (_, url, body) in FILES {
+        transport.insert(url, StatusCode::OK, body);
+    }
+    Client::builder()
+        .cdn_rate_limit(RateLimit::unlimited())
+        .retry_policy(RetryPolicy::none())
+        .build_with_transport(transport)
+        .unwrap()
+}
+
+async fn installed(root: &Path, client: &Client<MemoryTransport>) -> Library {
+    let mut library = Library::open(root).await.unwrap();
+    let report = library.install(client, &info(), &files(), &Selection::default()).run().await.unwrap();
+    assert!(report.is_success(), "{:?}", report);
+    library
+}
+
+fn sorted(mut paths: Vec<String>) -> Vec<String> {
+    paths.sort();
+    paths
+}
+
+#[tokio::test]
+async fn install_downloads_every_selected_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut library = Library::open(dir.path()).await.unwrap();
+    let client = client();
+    let selection = Selection { formats: vec!["hdr".to_string()], ..Selection::default() };
+    let report = library.install(&client, &info(), &files(), &selection).run().await.unwrap();
+
+    assert_eq!(sorted(report.downloaded), [FILES[1].0, FILES[2].0]);
+    assert_eq!(std::fs::read(dir.path().join(FILES[1].0)).unwrap(), FILES[1].2.as_bytes());
+    assert!(!dir.path().join(FILES[0].0).exists());
+    assert_eq!(library.resolutions("sky"), ["1k".parse().unwrap(), "2k".parse().unwrap()]);
+    assert!(library.is_available("sky", "2k".parse().unwrap(), "hdr"));
+    assert!(!library.is_available("sky", "1k".parse().unwrap(), "exr"));
+}
+
+#[tokio::test]
+async fn reinstall_downloads_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = client();
+    let mut library = installed(dir.path(), &client).await;
+    let requests = client.transport().requests().len();
+    let report = library.install(&client, &info(), &files(), &Selection::default()).run().await.unwrap();
+
+    assert!(report.downloaded.is_empty());
+    assert_eq!(sorted(report.unchanged), FILES.map(|(path, _, _)| path));
+    assert_eq!(client.transport().requests().len(), requests);
+}
+
+#[tokio::test]
+async fn modified_files_are_kept_as_conflicts() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut library = installed(dir.path(), &client()).await;
+    std::fs::write(dir.path().join(FILES[0].0), "edited").unwrap();
+    let report = library.remove("sky", &Selection::default()).await.unwrap();
+
+    assert_eq!(report.conflicts, [FILES[0].0]);
+    assert_eq!(sorted(report.removed), [FILES[1].0, FILES[2].0]);
+    assert_eq!(std::fs::read(dir.path().join(FILES[0].0)).unwrap(), b"edited");
+    assert!(!dir.path().join("hdris/sky/2k").exists());
+    let files = &library.asset("sky").unwrap().files;
+    assert_eq!(files.keys().collect::<Vec<_>>(), [FILES[0].0]);
+}
+
+#[tokio::test]
+async fn unreadable_file_does_not_stop_a_removal() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut library = installed(dir.path(), &client()).await;
+    // Files are removed in path order, so this one is in the middle. A
+    // directory can't be hashed, even by root.
+    let unreadable = dir.path().join(FILES[1].0);
+    std::fs::remove_file(&unreadable).unwrap();
+    std::fs::create_dir(&unreadable).unwrap();
+    let report = library.remove("sky", &Selection::default()).await.unwrap();
+
+    assert_eq!(report.removed, [FILES[0].0, FILES[2].0]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, FILES[1].0);
+    assert!(matches!(&*report.failed[0].1, Error::Io { path, .. } if *path == unreadable));
+    assert!(!report.is_success());
+
+    // The files that were removed are gone from the saved manifest too.
+    let reopened = Library::open(dir.path()).await.unwrap();
+    let files = &reopened.asset("sky").unwrap().files;
+    assert_eq!(files.keys().collect::<Vec<_>>(), [FILES[1].0]);
+}
+
+#[tokio::test]
+async fn manifest_round_trips() {
+    let dir = tempfile::tempdir().unwrap();
+    let library = installed(dir.path(), &client()).await;
+    let reopened = Library::open(dir.path()).await.unwrap();
+
+    assert_eq!(reopened.manifest(), library.manifest());
+    let asset = reopened.asset("sky").unwrap();
+    assert_eq!(asset.name, "Sky");
+    assert_eq!(asset.date_published, info().date_published);
+    assert_eq!(asset.files[FILES[0].0].format, "exr");
+    assert!(!dir.path().join("manifest.json.part").exists());
+}
+
+#[tokio::test]
+async fn unsafe_asset_id_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut library = Library::open(dir.path()).await.unwrap();
+    let client = client();
+    let info = AssetInfo { id: "C:sky".to_string(), ..info() };
+    let err = library.install(&client, &info, &files(), &Selection::default()).run().await.unwrap_err();
+
+    assert!(matches!(err, Error::UnsafePath(id) if id == "C:sky"));
+    assert!(client.transport().requests().is_empty());
+}
+
+#[tokio::test]
+async fn repair_downloads_exactly_the_broken_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = client();
+    let library = installed(dir.path(), &client).await;
+    std::fs::write(dir.path().join(FILES[0].0), "1k EXR").unwrap();
+    std::fs::remove_file(dir.path().join(FILES[2].0)).unwrap();
+    let report = library.verify().run().await.unwrap();
+
+    assert_eq!(report.checked, 3);
+    assert_eq!(report.missing, [FILES[2].0]);
+    assert_eq!(report.corrupted.iter().map(|corrupted| corrupted.path.as_str()).collect::<Vec<_>>(), [FILES[0].0]);
+    assert_eq!(report.corrupted[0].md5.as_deref(), Some(format!("{:x}", Md5::digest("1k EXR")).as_str()));
+    assert!(report.extra.is_empty() && report.errors.is_empty());
+
+    let requests = client.transport().requests().len();
+    let repaired = library.repair(&client, &report).run().await;
+    assert!(repaired.is_success());
+    let mut repaired = repaired.jobs.iter().map(|job| job.file.url.as_str()).collect::<Vec<_>>();
+    repaired.sort();
+    assert_eq!(repaired, [FILES[0].1, FILES[2].1]);
+    let mut requested = client.transport().requests()[requests..].iter().map(|request| request.url.to_string()).collect::<Vec<_>>();
+    requested.sort();
+    assert_eq!(requested, [FILES[0].1, FILES[2].1]);
+    assert!(library.verify().run().await.unwrap().is_ok());
+}
\ No newline at end of file
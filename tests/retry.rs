@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use polyhaven::{
+    data::asset::AssetType,
+    request::categories,
+    transport::{HttpResponse, MemoryTransport},
+    Client, Error, RateLimit, RetryPolicy
+};
+use reqwest::{header::{HeaderMap, HeaderValue, RETRY_AFTER}, StatusCode};
+
+const URL: &str = "https://api.polyhaven.com/categories/hdris";
+
+/// Answers the first `failures` requests with 503 and a `Retry-After`, and
+/// every request after that with a small JSON object.
+fn unavailable(failures: u32, retry_after: &'static str) -> MemoryTransport {
+    let transport = MemoryTransport::new().with_json(URL, r#"{"all": 1}"#);
+    for _ in 0..failures {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static(retry_after));
+        transport.push(URL, HttpResponse { status: StatusCode::SERVICE_UNAVAILABLE, headers, body: String::from("busy").into() });
+    }
+    transport
+}
+
+fn client(transport: MemoryTransport, policy: RetryPolicy) -> Client<MemoryTransport> {
+    Client::builder()
+        .api_rate_limit(RateLimit::unlimited())
+        .retry_policy(policy)
+        .build_with_transport(transport)
+        .unwrap()
+}
+
+async fn fetch(client: &Client<MemoryTransport>) -> polyhaven::Result<()> {
+    let params = categories::Params { asset_type: AssetType::HDRI, in_categories: vec![] };
+    client.categories(&params).await.map(|_| ())
+}
+
+#[tokio::test(start_paused = true)]
+async fn waits_for_retry_after_longer_than_max_delay() {
+    let client = client(unavailable(2, "60"), RetryPolicy::default());
+    let start = tokio::time::Instant::now();
+    fetch(&client).await.unwrap();
+
+    assert_eq!(client.transport().requests().len(), 3);
+    let waited = start.elapsed();
+    assert!(waited >= Duration::from_secs(120), "waited {:?}", waited);
+    assert!(waited < Duration::from_secs(121), "waited {:?}", waited);
+}
+
+#[tokio::test(start_paused = true)]
+async fn gives_up_when_retry_after_exceeds_max_retry_after() {
+    let policy = RetryPolicy { max_retry_after: Duration::from_secs(60), ..RetryPolicy::default() };
+    let client = client(unavailable(1, "600"), policy);
+    let err = fetch(&client).await.unwrap_err();
+
+    assert!(matches!(err, Error::Status { status: StatusCode::SERVICE_UNAVAILABLE, retry_after: Some(after), .. } if after == Duration::from_secs(600)));
+    assert_eq!(client.transport().requests().len(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn stops_after_max_attempts() {
+    let client = client(unavailable(5, "1"), RetryPolicy::default());
+    let err = fetch(&client).await.unwrap_err();
+
+    assert!(matches!(err, Error::Status { status: StatusCode::SERVICE_UNAVAILABLE, .. }));
+    assert_eq!(client.transport().requests().len(), 3);
+}
\ No newline at end of file
@@ -0,0 +1,60 @@
+#![cfg(feature = "serde")]
+
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use polyhaven::data::{
+    asset::{Asset, AssetInfo, AssetType, HDRIAsset, ModelAsset},
+    files::{FileData, Files, HDRIFormat, ModelFiles, Resolution, TextureFormat, TextureMap}
+};
+use serde_json::json;
+
+fn round_trip<T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug>(value: T, expected: serde_json::Value) {
+    let json = serde_json::to_value(&value).unwrap();
+    assert_eq!(json, expected);
+    assert_eq!(serde_json::from_value::<T>(json).unwrap(), value);
+}
+
+#[test]
+fn enums_are_readable_strings() {
+    round_trip(AssetType::Texture, json!("textures"));
+    round_trip(HDRIFormat::Exr, json!("exr"));
+    round_trip(TextureMap::Diffuse, json!("diffuse"));
+    round_trip(TextureFormat::Unparsed("tga".to_string()), json!("tga"));
+    round_trip("4k".parse::<Resolution>().unwrap(), json!("4k"));
+    // The singular names are accepted too.
+    assert_eq!(serde_json::from_value::<AssetType>(json!("hdri")).unwrap(), AssetType::HDRI);
+}
+
+#[test]
+fn asset_info_is_tagged_by_type() {
+    let info = AssetInfo {
+        id: "sky".to_string(),
+        name: "Sky".to_string(),
+        date_published: DateTime::from_timestamp(1_600_000_000, 0).unwrap(),
+        download_count: 10,
+        authors: HashMap::from([("Greg Zaal".to_string(), "All".to_string())]),
+        donated: false,
+        categories: vec!["outdoor".to_string()],
+        tags: vec![],
+        asset: Asset::HDRI(HDRIAsset { whitebalance: Some(6500), backplates: false, evs_cap: 12, coords: None })
+    };
+    let json = serde_json::to_value(&info).unwrap();
+    assert_eq!(json["asset"]["type"], "hdri");
+    assert_eq!(json["date_published"], "2020-09-13T12:26:40Z");
+    assert_eq!(serde_json::from_value::<AssetInfo>(json).unwrap(), info);
+
+    let model = AssetInfo { asset: Asset::Model(ModelAsset), ..info };
+    assert_eq!(serde_json::from_value::<AssetInfo>(serde_json::to_value(&model).unwrap()).unwrap(), model);
+}
+
+#[test]
+fn files_round_trip() {
+    let file = FileData { url: "https://dl.polyhaven.org/chair_1k.gltf".to_string(), md5: "0".repeat(32), size: 100, include: HashMap::new() };
+    let resolution = "1k".parse::<Resolution>().unwrap();
+    let files = Files::Model(ModelFiles { blend: HashMap::new(), gltf: HashMap::from([(resolution, file)]), fbx: HashMap::new(), maps: HashMap::new() });
+    let json = serde_json::to_value(&files).unwrap();
+
+    assert_eq!(json["model"]["gltf"]["1k"]["size"], 100);
+    assert_eq!(serde_json::from_value::<Files>(json).unwrap(), files);
+}
\ No newline at end of file
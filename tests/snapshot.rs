@@ -0,0 +1,98 @@
+use std::{collections::HashMap, path::Path};
+
+use polyhaven::{
+    data::asset::AssetType,
+    request::{assets, categories},
+    snapshot::{Snapshot, SnapshotTransport},
+    Client, Error
+};
+use serde_json::json;
+
+fn snapshot() -> Snapshot {
+    let mut snapshot = Snapshot::new("https://api.polyhaven.com");
+    let asset = |asset_type: i32, name: &str, author: &str, categories: &[&str], tags: &[&str]| json!({
+        "type": asset_type,
+        "name": name,
+        "date_published": 1_600_000_000,
+        "download_count": 10,
+        "authors": { author: "All" },
+        "categories": categories,
+        "tags": tags
+    });
+    snapshot.assets.insert("sky".to_string(), asset(0, "Sky", "Greg Zaal", &["outdoor", "skies"], &["clouds"]));
+    snapshot.assets.insert("studio".to_string(), asset(0, "Studio", "Greg Zaal", &["indoor", "studio"], &["softbox"]));
+    snapshot.assets.insert("chair".to_string(), asset(2, "Wooden Chair", "Rico Cilliers", &["furniture"], &["wood"]));
+    snapshot
+}
+
+fn params(asset_type: Option<AssetType>) -> assets::Params {
+    assets::Params { asset_type, categories: vec![], author: None, search: vec![] }
+}
+
+async fn ids(client: &Client<SnapshotTransport>, params: assets::Params) -> Vec<String> {
+    let mut ids = client.assets(&params).await.unwrap().into_keys().collect::<Vec<_>>();
+    ids.sort();
+    ids
+}
+
+async fn assert_serves_snapshot(snapshot: Snapshot) {
+    let client = Client::offline(snapshot);
+
+    assert_eq!(ids(&client, params(None)).await, ["chair", "sky", "studio"]);
+    assert_eq!(ids(&client, params(Some(AssetType::HDRI))).await, ["sky", "studio"]);
+    assert_eq!(ids(&client, assets::Params { categories: vec!["Outdoor".to_string()], ..params(None) }).await, ["sky"]);
+    assert_eq!(ids(&client, assets::Params { search: vec!["soft".to_string()], ..params(None) }).await, ["studio"]);
+    assert_eq!(ids(&client, assets::Params { author: Some("Rico Cilliers".to_string()), ..params(None) }).await, ["chair"]);
+
+    let counts = client.categories(&categories::Params { asset_type: AssetType::HDRI, in_categories: vec!["outdoor".to_string()] }).await.unwrap();
+    let expected = [("all", 1), ("outdoor", 1), ("skies", 1)].map(|(category, count)| (category.to_string(), count));
+    assert_eq!(counts, HashMap::from(expected));
+
+    assert_eq!(client.info("chair").await.unwrap().name, "Wooden Chair");
+    assert!(matches!(client.info("missing").await, Err(Error::NotFound { .. })));
+}
+
+#[tokio::test]
+async fn saved_snapshot_serves_the_same_queries() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snapshot.json");
+    snapshot().save(&path).await.unwrap();
+
+    assert_serves_snapshot(Snapshot::load(&path).await.unwrap()).await;
+}
+
+#[tokio::test]
+async fn snapshot_saved_as_a_directory_serves_the_same_queries() {
+    let dir = tempfile::tempdir().unwrap();
+    snapshot().save_dir(dir.path()).await.unwrap();
+
+    assert!(dir.path().join("files").is_dir());
+    assert_serves_snapshot(Snapshot::load(dir.path()).await.unwrap()).await;
+}
+
+async fn assert_invalid(path: &Path) {
+    let err = Snapshot::load(path).await.unwrap_err();
+    assert!(matches!(&err, Error::InvalidSnapshot { path: invalid, .. } if invalid.starts_with(path)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn malformed_snapshot_is_invalid() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snapshot.json");
+    std::fs::write(&path, r#"{"version": 1, "assets": []}"#).unwrap();
+    assert_invalid(&path).await;
+
+    std::fs::write(&path, "not JSON").unwrap();
+    assert_invalid(&path).await;
+}
+
+#[tokio::test]
+async fn snapshot_from_a_newer_version_is_invalid() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snapshot.json");
+    let mut value = serde_json::to_value(snapshot()).unwrap();
+    value["version"] = json!(1000);
+    std::fs::write(&path, value.to_string()).unwrap();
+
+    assert_invalid(&path).await;
+}
\ No newline at end of file
@@ -0,0 +1,128 @@
+use md5::{Digest, Md5};
+use polyhaven::{
+    library::{Library, Selection},
+    snapshot::Snapshot,
+    sync::{ApplyOptions, CatalogDiff, MetadataChange},
+    transport::MemoryTransport,
+    Client, RateLimit, RetryPolicy
+};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+const CDN: &str = "https://dl.polyhaven.org/file/ph-assets/HDRIs/hdr";
+
+fn hdri(name: &str, date_published: i64) -> Value {
+    json!({
+        "type": 0,
+        "name": name,
+        "date_published": date_published,
+        "download_count": 10,
+        "authors": { "Greg Zaal": "All" },
+        "categories": ["outdoor"],
+        "tags": []
+    })
+}
+
+fn md5(body: &str) -> String {
+    format!("{:x}", Md5::digest(body))
+}
+
+/// A `/files` response with a file at each `(resolution, body)`.
+fn hdri_files(id: &str, files: &[(&str, &str)]) -> Value {
+    let hdri = files.iter()
+        .map(|(resolution, body)| {
+            let file = json!({ "url": url(id, resolution), "md5": md5(body), "size": body.len() });
+            (resolution.to_string(), json!({ "hdr": file }))
+        })
+        .collect::<serde_json::Map<_, _>>();
+    json!({ "hdri": hdri, "backplates": {} })
+}
+
+fn url(id: &str, resolution: &str) -> String {
+    format!("{}/{}/{}_{}.hdr", CDN, resolution, id, resolution)
+}
+
+fn snapshot(assets: &[(&str, Value, Value)]) -> Snapshot {
+    let mut snapshot = Snapshot::new("https://api.polyhaven.com");
+    for (id, asset, files) in assets {
+        snapshot.assets.insert(id.to_string(), asset.clone());
+        snapshot.files.insert(id.to_string(), files.clone());
+    }
+    snapshot
+}
+
+#[test]
+fn diff_finds_added_removed_and_changed_assets() {
+    let old = snapshot(&[
+        ("sky", hdri("Sky", 100), hdri_files("sky", &[("1k", "sky v1")])),
+        ("studio", hdri("Studio", 100), hdri_files("studio", &[("1k", "studio")])),
+        ("dusk", hdri("Dusk", 100), hdri_files("dusk", &[("1k", "dusk")])),
+        ("noon", hdri("Noon", 100), hdri_files("noon", &[("1k", "noon")]))
+    ]);
+    let mut busier = hdri("Noon", 100);
+    busier["download_count"] = json!(5000);
+    let new = snapshot(&[
+        // A new hash for the same file.
+        ("sky", hdri("Sky", 100), hdri_files("sky", &[("1k", "sky v2")])),
+        // Re-released and renamed, with the same files.
+        ("studio", hdri("Studio Lights", 200), hdri_files("studio", &[("1k", "studio")])),
+        // Only downloaded more, which isn't a change.
+        ("noon", busier, hdri_files("noon", &[("1k", "noon")])),
+        ("forest", hdri("Forest", 300), hdri_files("forest", &[("1k", "forest")]))
+    ]);
+    let diff = CatalogDiff::between(&old, &new);
+
+    assert_eq!(diff.added, ["forest"]);
+    assert_eq!(diff.removed, ["dusk"]);
+    assert_eq!(diff.files_changed, ["sky"]);
+    assert_eq!(diff.metadata_changed, [MetadataChange {
+        id: "studio".to_string(),
+        fields: vec!["date_published".to_string(), "name".to_string()]
+    }]);
+    assert_eq!(diff.changed(), ["sky", "studio"]);
+    assert!(CatalogDiff::between(&new, &new).is_empty());
+}
+
+#[tokio::test]
+async fn apply_updates_a_library() {
+    let old = snapshot(&[
+        ("sky", hdri("Sky", 100), hdri_files("sky", &[("1k", "sky 1k v1"), ("2k", "sky 2k")])),
+        ("dusk", hdri("Dusk", 100), hdri_files("dusk", &[("1k", "dusk")]))
+    ]);
+    let new = snapshot(&[
+        // The 1k file was replaced and the 2k file dropped.
+        ("sky", hdri("Sky", 200), hdri_files("sky", &[("1k", "sky 1k v2")])),
+        ("forest", hdri("Forest", 300), hdri_files("forest", &[("1k", "forest")]))
+    ]);
+    let transport = MemoryTransport::new();
+    for (id, resolution, body) in [("sky", "1k", "sky 1k v1"), ("sky", "2k", "sky 2k"), ("dusk", "1k", "dusk"), ("forest", "1k", "forest")] {
+        transport.insert(&url(id, resolution), StatusCode::OK, body);
+    }
+    let client = Client::builder()
+        .cdn_rate_limit(RateLimit::unlimited())
+        .retry_policy(RetryPolicy::none())
+        .build_with_transport(transport)
+        .unwrap();
+
+    // Install everything from the old snapshot.
+    let dir = tempfile::tempdir().unwrap();
+    let mut library = Library::open(dir.path()).await.unwrap();
+    let options = ApplyOptions { selection: Selection::default(), install_added: true, remove_removed: true };
+    let report = library.apply(&client, &old, &CatalogDiff::between(&Snapshot::new("https://api.polyhaven.com"), &old), &options).await.unwrap();
+    assert!(report.is_success(), "{:?}", report);
+    assert_eq!(library.manifest().assets.keys().collect::<Vec<_>>(), ["dusk", "sky"]);
+
+    client.transport().insert(&url("sky", "1k"), StatusCode::OK, "sky 1k v2");
+    let report = library.apply(&client, &new, &CatalogDiff::between(&old, &new), &options).await.unwrap();
+
+    assert!(report.is_success(), "{:?}", report);
+    assert_eq!(report.removed["dusk"].removed, ["hdris/dusk/1k/dusk_1k.hdr"]);
+    assert_eq!(report.removed["sky"].removed, ["hdris/sky/2k/sky_2k.hdr"]);
+    assert_eq!(report.installed["sky"].downloaded, ["hdris/sky/1k/sky_1k.hdr"]);
+    assert_eq!(report.installed["forest"].downloaded, ["hdris/forest/1k/forest_1k.hdr"]);
+    assert_eq!(library.manifest().assets.keys().collect::<Vec<_>>(), ["forest", "sky"]);
+    assert_eq!(library.asset("sky").unwrap().date_published.timestamp(), 200);
+    assert_eq!(std::fs::read(dir.path().join("hdris/sky/1k/sky_1k.hdr")).unwrap(), b"sky 1k v2");
+    assert!(!dir.path().join("hdris/sky/2k").exists());
+    assert!(!dir.path().join("hdris/dusk").exists());
+}
\ No newline at end of file